@@ -2,11 +2,67 @@ use core::panic;
 
 use rand::distributions::{Uniform, Distribution};
 
+/// How a stamped pattern combines with the cells already on the board.
+#[derive(Clone, Copy, PartialEq, Default)]
+pub enum BlendMode {
+    /// The pattern's live and dead cells both replace the board's.
+    #[default]
+    Overwrite,
+    /// Live cells are added; nothing is erased.
+    Or,
+    /// Live cells toggle whatever is underneath.
+    Xor,
+    /// Live cells erase whatever is underneath.
+    AndNot,
+}
+
+impl BlendMode {
+    pub fn next(self) -> Self {
+        match self {
+            BlendMode::Overwrite => BlendMode::Or,
+            BlendMode::Or => BlendMode::Xor,
+            BlendMode::Xor => BlendMode::AndNot,
+            BlendMode::AndNot => BlendMode::Overwrite,
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            BlendMode::Overwrite => "overwrite",
+            BlendMode::Or => "OR",
+            BlendMode::Xor => "XOR",
+            BlendMode::AndNot => "AND-NOT",
+        }
+    }
+
+    pub fn blend(&self, existing: bool, pattern: bool) -> bool {
+        match self {
+            BlendMode::Overwrite => pattern,
+            BlendMode::Or => existing || pattern,
+            BlendMode::Xor => existing != pattern,
+            BlendMode::AndNot => existing && !pattern,
+        }
+    }
+}
+
 pub struct Board {
     pub width: i32,
     pub height: i32,
     pub cells: Vec<Vec<bool>>,
     pub new_cells: Vec<Vec<bool>>,
+    /// Whether the edges join up into a torus. A bounded board treats
+    /// everything past its edges as dead.
+    pub wrap: bool,
+}
+
+/// Every cell of a `[y][x]` pattern placed with its top-left corner at
+/// `origin`, in board coordinates, and whether it is alive.
+pub fn pattern_cells(origin: (i32, i32), cells: &[Vec<u8>]) -> impl Iterator<Item = ((i32, i32), bool)> + '_ {
+    cells.iter().enumerate().flat_map(move |(y, row)| {
+        row.iter()
+            .enumerate()
+            .map(move |(x, cell)| ((origin.0 + x as i32, origin.1 + y as i32), *cell != 0))
+    })
 }
 
 impl Board {
@@ -16,6 +72,7 @@ impl Board {
             height,
             cells: vec![vec![false; height as usize]; width as usize],
             new_cells: vec![vec![false; height as usize]; width as usize],
+            wrap: true,
         }
     }
 
@@ -25,11 +82,10 @@ impl Board {
         for row in x - 1..=x + 1 {
             for col in y - 1..=y + 1 {
                 if row != x || col != y {
-                    let new_row = row.rem_euclid(self.height);
-                    let new_col = col.rem_euclid(self.width);
-
-                    if self.cells[new_col as usize][new_row as usize] {
-                        count += 1;
+                    if let Some((new_col, new_row)) = self.wrap_coords(col, row) {
+                        if self.cells[new_col as usize][new_row as usize] {
+                            count += 1;
+                        }
                     }
                 }
             }
@@ -38,6 +94,39 @@ impl Board {
         count
     }
 
+    /// Whether a cell position is on the board as it is, without wrapping.
+    pub fn contains(&self, x: i32, y: i32) -> bool {
+        (0..self.width).contains(&x) && (0..self.height).contains(&y)
+    }
+
+    /// Maps a cell position onto the board, wrapping it around the torus or,
+    /// on a bounded board, dropping it if it falls off the edge.
+    pub fn wrap_coords(&self, x: i32, y: i32) -> Option<(i32, i32)> {
+        if self.wrap {
+            Some((x.rem_euclid(self.width), y.rem_euclid(self.height)))
+        } else if self.contains(x, y) {
+            Some((x, y))
+        } else {
+            None
+        }
+    }
+
+    /// Stamps a `[y][x]` pattern with its top-left corner at `origin`,
+    /// wrapping or clipping at the edges.
+    pub fn stamp(&mut self, origin: (i32, i32), cells: &[Vec<u8>], mode: BlendMode) {
+        for (point, alive) in pattern_cells(origin, cells) {
+            self.blend_cell(point, alive, mode);
+        }
+    }
+
+    /// Blends one cell into the board, if it lands on it after wrapping.
+    pub fn blend_cell(&mut self, (x, y): (i32, i32), alive: bool, mode: BlendMode) {
+        if let Some((x, y)) = self.wrap_coords(x, y) {
+            let cell = &mut self.cells[x as usize][y as usize];
+            *cell = mode.blend(*cell, alive);
+        }
+    }
+
     pub fn evolve_cell(&self, x: i32, y: i32) -> bool {
         let neighbors = self.get_neighbor_count(x, y);
 
@@ -70,6 +159,29 @@ impl Board {
         }
     }
 
+    pub fn live_cells(&self) -> Vec<(i32, i32)> {
+        let mut live = vec![];
+
+        for (x, col) in self.cells.iter().enumerate() {
+            for (y, cell) in col.iter().enumerate() {
+                if *cell {
+                    live.push((x as i32, y as i32));
+                }
+            }
+        }
+
+        live
+    }
+
+    pub fn set_live_cells(&mut self, live: &[(i32, i32)]) {
+        self.reset();
+        for &(x, y) in live {
+            if self.contains(x, y) {
+                self.cells[x as usize][y as usize] = true;
+            }
+        }
+    }
+
     pub fn randomize(&mut self) {
         let mut rng = rand::thread_rng();
         let dist = Uniform::from(0..=1);
@@ -0,0 +1,160 @@
+use std::{
+    fs,
+    path::Path,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use sdl2::{image::SaveSurface, pixels::Color, pixels::PixelFormatEnum, rect::Rect, surface::Surface};
+
+use crate::game_of_life::board::Board;
+
+pub const EXPORT_DIR: &str = "exports";
+/// Largest image exports and recordings will allocate, in pixels (128 MB
+/// of RGBA).
+const MAX_IMAGE_PIXELS: usize = 1 << 25;
+
+#[derive(Clone, Copy)]
+pub struct Theme {
+    pub alive: Color,
+    pub dead: Color,
+    pub bg: Color,
+}
+
+impl Theme {
+    pub fn light() -> Self {
+        Theme {
+            alive: Color::RGB(0x17, 0x17, 0x17),
+            dead: Color::RGB(0xF7, 0xF7, 0xF7),
+            bg: Color::RGB(0x7F, 0x7F, 0x7F),
+        }
+    }
+
+    pub fn dark() -> Self {
+        Theme {
+            alive: Color::RGB(0xA7, 0xA7, 0xA7),
+            dead: Color::RGB(0x17, 0x17, 0x17),
+            bg: Color::RGB(0x27, 0x27, 0x27),
+        }
+    }
+}
+
+/// A plain RGBA32 pixel buffer, so exports don't depend on a window or
+/// renderer being around.
+pub struct Image {
+    pub width: u32,
+    pub height: u32,
+    pub pixels: Vec<u8>,
+}
+
+impl Image {
+    pub fn new(width: u32, height: u32, color: Color) -> Result<Self, String> {
+        let count = pixel_count(width, height)?;
+        let mut pixels = Vec::with_capacity(count * 4);
+        for _ in 0..count {
+            pixels.extend_from_slice(&[color.r, color.g, color.b, color.a]);
+        }
+
+        Ok(Image { width, height, pixels })
+    }
+
+    pub fn fill_rect(&mut self, rect: Rect, color: Color) {
+        let x0 = rect.x().clamp(0, self.width as i32) as u32;
+        let y0 = rect.y().clamp(0, self.height as i32) as u32;
+        let x1 = (rect.x() + rect.width() as i32).clamp(0, self.width as i32) as u32;
+        let y1 = (rect.y() + rect.height() as i32).clamp(0, self.height as i32) as u32;
+
+        for y in y0..y1 {
+            for x in x0..x1 {
+                let idx = (y as usize * self.width as usize + x as usize) * 4;
+                self.pixels[idx..idx + 4].copy_from_slice(&[color.r, color.g, color.b, color.a]);
+            }
+        }
+    }
+
+    pub fn save_png(&mut self, path: &str) -> Result<(), String> {
+        let pitch = self.width * 4;
+        let surface = Surface::from_data(
+            &mut self.pixels,
+            self.width,
+            self.height,
+            pitch,
+            PixelFormatEnum::RGBA32,
+        )?;
+        surface.save(path)
+    }
+}
+
+fn pixel_count(width: u32, height: u32) -> Result<usize, String> {
+    (width as usize)
+        .checked_mul(height as usize)
+        .filter(|n| *n <= MAX_IMAGE_PIXELS)
+        .ok_or_else(|| format!("{}x{} pixels is too large to export", width, height))
+}
+
+/// The size in pixels of `region` drawn at `scale` pixels per cell, or an
+/// error if that is more than an export is allowed to allocate.
+pub fn scaled_size(region: Rect, scale: u32) -> Result<(u32, u32), String> {
+    let too_large = || {
+        format!(
+            "{}x{} cells at {} px per cell is too large to export",
+            region.width(),
+            region.height(),
+            scale
+        )
+    };
+    let width = region.width().checked_mul(scale).ok_or_else(too_large)?;
+    let height = region.height().checked_mul(scale).ok_or_else(too_large)?;
+    pixel_count(width, height).map_err(|_| too_large())?;
+    Ok((width, height))
+}
+
+/// Clips a region given in cell coordinates to the board, defaulting to the
+/// whole board. Fails if none of the region is on the board.
+pub fn clip_region(board: &Board, region: Option<Rect>) -> Result<Rect, String> {
+    let full = Rect::new(0, 0, board.width as u32, board.height as u32);
+    match region {
+        Some(r) => r
+            .intersection(full)
+            .ok_or_else(|| "the region is outside the board, nothing to export".to_string()),
+        None => Ok(full),
+    }
+}
+
+/// Draws `region` of the board at `scale` pixels per cell. Cells big enough
+/// to have one get the same background-coloured gutter as on screen.
+pub fn rasterize(board: &Board, region: Option<Rect>, scale: u32, theme: &Theme) -> Result<Image, String> {
+    let region = clip_region(board, region)?;
+    let scale = scale.max(1);
+    let (width, height) = scaled_size(region, scale)?;
+    let mut image = Image::new(width, height, theme.bg)?;
+    let inset = if scale >= 4 { 1 } else { 0 };
+
+    for x in 0..region.width() {
+        for y in 0..region.height() {
+            let alive = board.cells[(region.x() + x as i32) as usize][(region.y() + y as i32) as usize];
+            let cell_rect = Rect::new(
+                (x * scale + inset) as i32,
+                (y * scale + inset) as i32,
+                scale - inset * 2,
+                scale - inset * 2,
+            );
+            image.fill_rect(cell_rect, if alive { theme.alive } else { theme.dead });
+        }
+    }
+
+    Ok(image)
+}
+
+/// Picks a fresh file name under the export directory, creating it if needed.
+pub fn export_path(prefix: &str, generation: u64, ext: &str) -> String {
+    let _ = fs::create_dir_all(EXPORT_DIR);
+    let stamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis())
+        .unwrap_or(0);
+
+    Path::new(EXPORT_DIR)
+        .join(format!("{}_gen{}_{}.{}", prefix, generation, stamp, ext))
+        .to_string_lossy()
+        .into_owned()
+}
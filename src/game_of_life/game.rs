@@ -1,16 +1,38 @@
-use crate::game_of_life::board::Board;
+use crate::game_of_life::board::{BlendMode, Board};
+use crate::game_of_life::export::*;
+use crate::game_of_life::parse_value;
+use crate::game_of_life::history::*;
+use crate::game_of_life::image_import::*;
+use crate::game_of_life::interchange::*;
+use crate::game_of_life::placement::*;
+use crate::game_of_life::recording::*;
+use crate::game_of_life::rle::*;
+use crate::game_of_life::search::*;
+use crate::game_of_life::session::*;
+use crate::game_of_life::shapes::*;
+use crate::game_of_life::svg::*;
+use crate::game_of_life::transform::*;
 use crate::game_of_life::structures::*;
+use crate::game_of_life::ui::*;
 use sdl2::{
     event::Event,
     keyboard::{Keycode, Mod},
+    messagebox::{show_message_box, show_simple_message_box, ButtonData, ClickedButton, MessageBoxButtonFlag, MessageBoxFlag},
     mouse::MouseButton,
-    pixels::Color,
-    rect::Rect,
-    render::Canvas,
-    video::Window,
+    pixels::{Color, PixelFormatEnum},
+    rect::{Point, Rect},
+    render::{Canvas, TextureCreator},
+    ttf::Font,
+    video::{Window, WindowContext},
     Sdl,
 };
-use std::time::Duration;
+use std::{
+    collections::HashSet,
+    fs, io,
+    path::Path,
+    thread::{self, JoinHandle},
+    time::{Duration, Instant},
+};
 
 enum MoveDir {
     UP,
@@ -19,6 +41,36 @@ enum MoveDir {
     RIGHT
 }
 
+/// The typed structure search popup.
+struct Picker {
+    query: String,
+    /// (bank index, entry index) pairs, best match first.
+    results: Vec<(usize, usize)>,
+    cursor: usize,
+}
+
+const PICKER_ROWS: usize = 9;
+
+/// What a prompt in the window title is asking for.
+#[derive(Clone, Copy, PartialEq)]
+enum PromptKind {
+    /// A name to add the selection to the active bank under.
+    StructureName,
+    ArraySettings,
+    ImportPath,
+}
+
+/// A line of text being typed into the window title.
+struct Prompt {
+    kind: PromptKind,
+    text: String,
+}
+
+enum ExportKind {
+    Screen,
+    Scaled,
+}
+
 pub struct Game {
     board: Board,
     color_alive: Color,
@@ -26,7 +78,22 @@ pub struct Game {
     color_bg: Color,
     color_ghost_alive: Color,
     color_ghost_dead: Color,
+    blend_mode: BlendMode,
+    sticky_stamp: bool,
+    tool: Tool,
+    brush: Brush,
+    history: History,
+    symmetry: Symmetry,
+    symmetry_centre: (i32, i32),
+    shape_start: Option<(i32, i32)>,
+    shape_alive: bool,
+    array: Option<ArraySettings>,
     color_cursor: Color,
+    color_selection: Color,
+    color_panel: Color,
+    color_text: Color,
+    color_text_dim: Color,
+    color_highlight: Color,
     dark_mode: bool,
 
     screen_width: u32,
@@ -42,82 +109,1402 @@ pub struct Game {
     cam_offset_y: i32,
     move_dir: Option<MoveDir>,
 
-    structures: Structures,
+    banks: Vec<Structures>,
+    bank_idx: usize,
+    bank_dir: String,
+    last_bank_check: Instant,
     strctr_selected: bool,
     strctr_idx: usize,
     strctr_cursor: Vec<Vec<u8>>,
+    strctr_base: Vec<Vec<u8>>,
+    strctr_orientation: Orientation,
+    strctr_filter: StructureFilter,
+    show_sidebar: bool,
+    sidebar_scroll: i32,
 
     cursor_rect: Rect,
     last_selected: Option<bool>,
 
-    run_sim: bool,
-    denom: i32,
+    selection: Option<Rect>,
+    selection_mask: Option<HashSet<(i32, i32)>>,
+    wand_reach: i32,
+    clipboard: Option<Vec<Vec<u8>>>,
+    select_anchor: Option<(i32, i32)>,
+    prompt: Option<Prompt>,
+    picker: Option<Picker>,
+
+    run_sim: bool,
+    denom: i32,
+
+    export_scale: u32,
+    pending_export: Option<(ExportKind, Option<Rect>)>,
+
+    import_path: String,
+    import_settings: ImportSettings,
+
+    recorder: Option<Recorder>,
+    record_settings: RecordSettings,
+    record_each_frame: bool,
+    last_recorded_gen: Option<u64>,
+
+    autosave_path: String,
+    autosave_interval: Duration,
+    last_autosave: Instant,
+    autosave_job: Option<JoinHandle<io::Result<()>>>,
+
+    sdl_context: Sdl,
+    canvas: Canvas<Window>,
+    tex_width: u32,
+    tex_height: u32,
+    tex_offset: i32,
+}
+
+/// The cell rectangle spanned by two corners, inclusive.
+fn rect_between(a: (i32, i32), b: (i32, i32)) -> Rect {
+    Rect::new(
+        a.0.min(b.0),
+        a.1.min(b.1),
+        (a.0 - b.0).unsigned_abs() + 1,
+        (a.1 - b.1).unsigned_abs() + 1,
+    )
+}
+
+/// Settings for the windowed game, from the command line or environment.
+pub struct GameOptions {
+    /// Where saved structure banks go and are loaded from.
+    pub bank_dir: String,
+    pub autosave_interval: Duration,
+}
+
+impl Default for GameOptions {
+    fn default() -> Self {
+        GameOptions {
+            bank_dir: std::env::var("GOL_BANK_DIR").unwrap_or_else(|_| "banks".to_string()),
+            autosave_interval: Duration::from_secs(30),
+        }
+    }
+}
+
+/// Parses the arguments when not running headless.
+pub fn parse_args(args: &[String]) -> Result<GameOptions, String> {
+    let mut opts = GameOptions::default();
+    let mut args = args.iter();
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--bank-dir" => opts.bank_dir = parse_value(arg, args.next())?,
+            "--autosave-secs" => {
+                let secs: u64 = parse_value(arg, args.next())?;
+                if secs == 0 {
+                    return Err("--autosave-secs must be at least 1".to_string());
+                }
+                opts.autosave_interval = Duration::from_secs(secs);
+            }
+            _ => return Err(format!("unknown option \"{}\"", arg)),
+        }
+    }
+
+    Ok(opts)
+}
+
+impl Game {
+    pub fn with_size(width: u32, height: u32, opts: GameOptions) -> Self {
+        let sdl_context = sdl2::init().unwrap();
+        let video_subsystem = sdl_context.video().unwrap();
+        // SDL starts with text input on, which would type the key that
+        // opens a prompt into it. It's only turned on while a prompt is up.
+        video_subsystem.text_input().stop();
+
+        let window = video_subsystem
+            .window("Game of Life", width, height)
+            .position_centered()
+            .build()
+            .unwrap();
+
+        let mut canvas = window.into_canvas().accelerated().build().unwrap();
+        canvas.set_blend_mode(sdl2::render::BlendMode::Blend);
+
+        let cells_width = 300;
+        let cells_height = 300;
+        let cell_width = 12;
+        let cell_height = 12;
+
+        let bank_dir = opts.bank_dir;
+        let mut banks = load_banks("structures.json", &bank_dir);
+        if banks.is_empty() {
+            banks.push(Structures::empty("(none)"));
+        }
+
+        let tex_offset = 0;
+        let tex_width = 1000;
+        let tex_height = height - tex_offset as u32 * 2;
+
+        Game {
+            board: Board::with_size(cells_width, cells_height),
+            color_alive: Color::RGB(0x17, 0x17, 0x17),
+            color_dead: Color::RGB(0xF7, 0xF7, 0xF7),
+            color_bg: Color::RGB(0x7F, 0x7F, 0x7F),
+            color_ghost_alive: Color::RGBA(0, 0, 0xFF, 0x8F),
+            color_ghost_dead: Color::RGBA(0, 0, 0xFF, 0x2F),
+            blend_mode: BlendMode::Overwrite,
+            sticky_stamp: false,
+            tool: Tool::Freehand,
+            brush: Brush::default(),
+            history: History::new(64 * 1024 * 1024),
+            symmetry: Symmetry::None,
+            symmetry_centre: (cells_width, cells_height),
+            shape_start: None,
+            shape_alive: true,
+            array: None,
+            color_cursor: Color::RGBA(0xFF, 0, 0, 0x7F),
+            color_selection: Color::RGBA(0xFF, 0x8C, 0, 0xCF),
+            color_panel: Color::RGB(0x27, 0x2D, 0x36),
+            color_text: Color::RGB(0xE6, 0xE6, 0xE6),
+            color_text_dim: Color::RGB(0x9A, 0xA0, 0xA8),
+            color_highlight: Color::RGB(0x3A, 0x46, 0x58),
+            dark_mode: false,
+            screen_width: width,
+            screen_height: height,
+            cell_width: 12,
+            cell_height: 12,
+            generation: 0,
+            pan_cam: false,
+            cam_offset_x: 40,
+            cam_offset_y: 40,
+            move_dir: None,
+
+            banks,
+            bank_idx: 0,
+            bank_dir,
+            last_bank_check: Instant::now(),
+            strctr_selected: false,
+            strctr_idx: 0,
+            strctr_cursor: vec![],
+            strctr_base: vec![],
+            strctr_orientation: Orientation::default(),
+            strctr_filter: StructureFilter::default(),
+            show_sidebar: true,
+            sidebar_scroll: 0,
+
+            cursor_rect: Rect::new(0, 0, cell_width as u32, cell_height as u32),
+            last_selected: None,
+
+            selection: None,
+            selection_mask: None,
+            wand_reach: 1,
+            clipboard: None,
+            select_anchor: None,
+            prompt: None,
+            picker: None,
+
+            run_sim: false,
+            denom: 60,
+
+            export_scale: 8,
+            pending_export: None,
+
+            import_path: "import.png".to_string(),
+            import_settings: ImportSettings::default(),
+
+            recorder: None,
+            record_settings: RecordSettings::default(),
+            record_each_frame: false,
+            last_recorded_gen: None,
+
+            autosave_path: "autosave.json".to_string(),
+            autosave_interval: opts.autosave_interval,
+            last_autosave: Instant::now(),
+            autosave_job: None,
+
+            sdl_context,
+            canvas,
+            tex_width,
+            tex_height,
+            tex_offset,
+        }
+    }
+
+    fn apply_theme(&mut self) {
+        let theme = if self.dark_mode { Theme::dark() } else { Theme::light() };
+        self.color_alive = theme.alive;
+        self.color_dead = theme.dead;
+        self.color_bg = theme.bg;
+
+        if self.dark_mode {
+            self.color_ghost_alive = Color::RGBA(0, 0x96, 0xFF, 0x7F);
+            self.color_ghost_dead = Color::RGBA(0, 0x96, 0xFF, 0x3F);
+            self.color_cursor = Color::RGBA(0xFF, 0, 0, 0x7F);
+        } else {
+            self.color_ghost_alive = Color::RGBA(0, 0, 0xFF, 0x8F);
+            self.color_ghost_dead = Color::RGBA(0, 0, 0xFF, 0x2F);
+            self.color_cursor = Color::RGBA(0xFF, 0, 0, 0x7F);
+        }
+    }
+
+    /// Ghost colours for live and dead pattern cells in the current blend
+    /// mode. Dead cells only matter when overwriting, so other modes leave
+    /// them out.
+    fn ghost_colors(&self) -> (Color, Option<Color>) {
+        match self.blend_mode {
+            BlendMode::Overwrite => (self.color_ghost_alive, Some(self.color_ghost_dead)),
+            BlendMode::Or => (Color::RGBA(0, 0xB4, 0x3C, 0x8F), None),
+            BlendMode::Xor => (Color::RGBA(0xFF, 0x8C, 0, 0x8F), None),
+            BlendMode::AndNot => (Color::RGBA(0xE0, 0x20, 0x20, 0x8F), None),
+        }
+    }
+
+    fn to_session(&self) -> Session {
+        Session {
+            width: self.board.width,
+            height: self.board.height,
+            live_cells: self.board.live_cells(),
+            generation: self.generation,
+            cam_offset_x: self.cam_offset_x,
+            cam_offset_y: self.cam_offset_y,
+            cell_width: self.cell_width,
+            cell_height: self.cell_height,
+            dark_mode: self.dark_mode,
+            denom: self.denom,
+            strctr_idx: self.strctr_idx,
+            bank: Some(self.structures().name().to_string()),
+            wrap: self.board.wrap,
+        }
+    }
+
+    /// Swaps in a new board. The symmetry centre goes back to the middle if
+    /// the size changed, so it can't end up off the board.
+    fn set_board(&mut self, board: Board) {
+        if (board.width, board.height) != (self.board.width, self.board.height) {
+            self.symmetry_centre = (board.width, board.height);
+        }
+        self.board = board;
+    }
+
+    fn restore_session(&mut self, session: Session) {
+        self.set_board(session.to_board());
+        self.generation = session.generation;
+        self.cam_offset_x = session.cam_offset_x;
+        self.cam_offset_y = session.cam_offset_y;
+        self.cell_width = session.cell_width;
+        self.cell_height = session.cell_height;
+        self.cursor_rect.w = self.cell_width;
+        self.cursor_rect.h = self.cell_height;
+        self.dark_mode = session.dark_mode;
+        self.apply_theme();
+        self.denom = session.denom.max(1);
+        if let Some(idx) = self.banks.iter().position(|b| Some(b.name()) == session.bank.as_deref()) {
+            self.bank_idx = idx;
+        }
+        self.strctr_idx = session.strctr_idx.min(self.structures().len().saturating_sub(1));
+        self.refresh_title();
+    }
+
+    fn structures(&self) -> &Structures {
+        &self.banks[self.bank_idx]
+    }
+
+    fn bank_issue_count(&self) -> usize {
+        self.banks.iter().map(|b| b.issues().len()).sum()
+    }
+
+    /// Shows what went wrong loading the structure banks. Broken entries and
+    /// banks are skipped, so this is a warning rather than a reason to stop.
+    pub fn report_bank_issues(&self) {
+        let issues: Vec<&BankIssue> = self.banks.iter().flat_map(|b| b.issues()).collect();
+        if issues.is_empty() {
+            return;
+        }
+
+        let mut message: Vec<String> = issues.iter().take(20).map(|i| i.to_string()).collect();
+        if issues.len() > 20 {
+            message.push(format!("...and {} more", issues.len() - 20));
+        }
+        let _ = show_simple_message_box(
+            MessageBoxFlag::WARNING,
+            "Structure bank problems",
+            &message.join("\n"),
+            self.canvas.window(),
+        );
+    }
+
+    /// Keeps the window title showing the current bank entry, the active
+    /// filter and any bank problems.
+    pub fn refresh_title(&mut self) {
+        if let Some(prompt) = &self.prompt {
+            let label = match prompt.kind {
+                PromptKind::StructureName => format!("Name new structure in \"{}\"", self.structures().name()),
+                PromptKind::ArraySettings => {
+                    "Array placement (cols rows dx dy [generation offset], empty for off)".to_string()
+                }
+                PromptKind::ImportPath => "Import file (.png, .npy, .csv)".to_string(),
+            };
+            let title = format!("{}: {}_", label, prompt.text);
+            let _ = self.canvas.window_mut().set_title(&title);
+            return;
+        }
+        if let Some(picker) = &self.picker {
+            let title = format!("Find structure: {}_ ({} matches)", picker.query, picker.results.len());
+            let _ = self.canvas.window_mut().set_title(&title);
+            return;
+        }
+
+        let mut title = format!("Game of Life [{}]", self.structures().name());
+        if let Some(nickname) = self.structures().nickname(self.strctr_idx) {
+            title.push_str(&format!(" - {}", nickname));
+            let summary = self
+                .structures()
+                .metadata(self.strctr_idx)
+                .map(|m| m.summary())
+                .unwrap_or_default();
+            if !summary.is_empty() {
+                title.push_str(&format!(" ({})", summary));
+            }
+        }
+        if self.tool != Tool::Freehand {
+            title.push_str(&format!(" [{}]", self.tool.label()));
+        } else if self.brush.radius > 1 || self.brush.spray {
+            title.push_str(&format!(" [{}]", self.brush.describe()));
+        }
+        if self.symmetry != Symmetry::None {
+            title.push_str(&format!(" [symmetry: {}]", self.symmetry.label()));
+        }
+        if self.sticky_stamp {
+            title.push_str(" [repeat stamp]");
+        }
+        if let Some(array) = &self.array {
+            title.push_str(&format!(" [array {}]", array.describe()));
+        }
+        if self.blend_mode != BlendMode::Overwrite {
+            title.push_str(&format!(" [{}]", self.blend_mode.label()));
+        }
+        if !self.strctr_orientation.is_identity() {
+            title.push_str(&format!(" [{}]", self.strctr_orientation.label()));
+        }
+        if self.strctr_filter.is_active() {
+            title.push_str(&format!(" [filter: {}]", self.strctr_filter.describe()));
+        }
+        let issues = self.bank_issue_count();
+        if issues > 0 {
+            title.push_str(&format!(" - {} structure bank problem(s)", issues));
+        }
+
+        let _ = self.canvas.window_mut().set_title(&title);
+    }
+
+    /// Replaces the pattern under the cursor, keeping the current
+    /// orientation so a run of stamps can all face the same way.
+    fn set_ghost(&mut self, cells: Vec<Vec<u8>>) {
+        self.strctr_cursor = self.strctr_orientation.apply(&cells);
+        self.strctr_base = cells;
+    }
+
+    fn orient_ghost(&mut self, orientation: Orientation) {
+        if !self.strctr_selected {
+            return;
+        }
+        self.strctr_orientation = orientation;
+        self.strctr_cursor = orientation.apply(&self.strctr_base);
+        self.refresh_title();
+    }
+
+    /// Labels the ghost with its orientation while it is turned away from
+    /// the way the bank stores it.
+    fn draw_orientation(&mut self, texture_creator: &TextureCreator<WindowContext>, font: Option<&Font>, (x, y): (i32, i32)) {
+        let Some(font) = font else {
+            return;
+        };
+        if !self.strctr_selected || self.strctr_orientation.is_identity() || self.picker.is_some() {
+            return;
+        }
+        let label = self.strctr_orientation.label();
+        let _ = draw_text(&mut self.canvas, texture_creator, font, &label, (x + 14, y - 18), 200, self.color_highlight);
+    }
+
+    fn select_structure(&mut self, idx: usize) {
+        self.strctr_idx = idx;
+        if let Some(cells) = self.structures().get_cells(idx) {
+            self.set_ghost(cells);
+        }
+        self.refresh_title();
+    }
+
+    fn switch_bank(&mut self, idx: usize) {
+        self.bank_idx = idx % self.banks.len();
+        let idx = self.structures().first_matching(0, &self.strctr_filter).unwrap_or(0);
+        println!(
+            "structure bank: {} ({} entries, {})",
+            self.structures().name(),
+            self.structures().len(),
+            self.structures().path().unwrap_or("not saved")
+        );
+        if self.strctr_selected && idx < self.structures().len() {
+            self.select_structure(idx);
+        } else {
+            self.strctr_idx = idx;
+            self.strctr_selected = false;
+            self.refresh_title();
+        }
+    }
+
+    /// A path in the bank directory for a new bank, never an existing file.
+    fn new_bank_path(&self, name: &str) -> String {
+        let stem: String = name
+            .chars()
+            .map(|c| if c.is_alphanumeric() || c == '-' || c == '_' { c } else { '_' })
+            .collect();
+        let mut path = Path::new(&self.bank_dir).join(format!("{}.json", stem));
+        let mut n = 2;
+        while path.exists() {
+            path = Path::new(&self.bank_dir).join(format!("{}_{}.json", stem, n));
+            n += 1;
+        }
+        path.to_string_lossy().into_owned()
+    }
+
+    /// Writes new banks into the bank directory and adds them to the set.
+    /// Returns how many were written; the rest are left out.
+    fn add_banks(&mut self, banks: Vec<Structures>) -> usize {
+        if let Err(e) = fs::create_dir_all(&self.bank_dir) {
+            println!("could not create {}: {}", self.bank_dir, e);
+            return 0;
+        }
+        let mut added = 0;
+        for mut bank in banks {
+            let path = self.new_bank_path(bank.name());
+            match bank.save_to_json(&path) {
+                Ok(()) => {
+                    println!("wrote bank \"{}\" to {}", bank.name(), path);
+                    self.banks.push(bank);
+                    added += 1;
+                }
+                Err(e) => println!("could not write {}: {}", path, e),
+            }
+        }
+        added
+    }
+
+    fn merge_with_next_bank(&mut self) {
+        if self.banks.len() < 2 {
+            println!("need at least two banks to merge");
+            return;
+        }
+        let other = &self.banks[(self.bank_idx + 1) % self.banks.len()];
+        let name = format!("{} + {}", self.structures().name(), other.name());
+        let (merged, skipped) = self.structures().merge(other, &name);
+        if skipped > 0 {
+            println!("{} duplicate nickname(s) left out of \"{}\"", skipped, name);
+        }
+        if self.add_banks(vec![merged]) > 0 {
+            self.switch_bank(self.banks.len() - 1);
+        }
+    }
+
+    fn split_bank(&mut self) {
+        let banks = self.structures().split_by_category();
+        if banks.len() < 2 {
+            println!("\"{}\" only has one category, nothing to split", self.structures().name());
+            return;
+        }
+        self.add_banks(banks);
+    }
+
+    fn start_naming(&mut self) {
+        if self.selection.is_none() {
+            println!("select a region with shift + drag first");
+            return;
+        }
+        self.start_prompt(PromptKind::StructureName, String::new());
+    }
+
+    fn start_prompt(&mut self, kind: PromptKind, text: String) {
+        self.prompt = Some(Prompt { kind, text });
+        self.set_text_input(true);
+        self.refresh_title();
+    }
+
+    fn stop_prompt(&mut self) {
+        self.prompt = None;
+        self.set_text_input(false);
+        self.refresh_title();
+    }
+
+    /// Input while a title prompt is open. Returns true to quit, like
+    /// `do_input`.
+    fn prompt_input(&mut self, event: Event) -> bool {
+        let Some(prompt) = &mut self.prompt else {
+            return false;
+        };
+        match event {
+            Event::Quit { .. } => return true,
+            Event::TextInput { text, .. } => {
+                prompt.text.push_str(&text);
+                self.refresh_title();
+            }
+            Event::KeyDown { keycode: Some(Keycode::Backspace), .. } => {
+                prompt.text.pop();
+                self.refresh_title();
+            }
+            Event::KeyDown { keycode: Some(Keycode::Escape), .. } => {
+                self.stop_prompt();
+            }
+            Event::KeyDown { keycode: Some(Keycode::Return), .. } => {
+                let (kind, text) = (prompt.kind, prompt.text.clone());
+                self.submit_prompt(kind, &text);
+            }
+            _ => {}
+        }
+        false
+    }
+
+    /// Acts on an entered prompt. The prompt stays open if the text can't
+    /// be used, so it can be corrected.
+    fn submit_prompt(&mut self, kind: PromptKind, text: &str) {
+        match kind {
+            PromptKind::StructureName => match self.capture_selection(text) {
+                Ok(()) => self.stop_prompt(),
+                Err(e) => println!("could not add structure: {}", e),
+            },
+            PromptKind::ArraySettings if text.trim().is_empty() => {
+                self.array = None;
+                println!("array placement off");
+                self.stop_prompt();
+            }
+            PromptKind::ArraySettings => match ArraySettings::parse(text) {
+                Ok(array) => {
+                    println!("array placement: {}", array.describe());
+                    self.array = Some(array);
+                    self.stop_prompt();
+                }
+                Err(e) => println!("bad array settings: {}", e),
+            },
+            PromptKind::ImportPath if text.trim().is_empty() => {}
+            PromptKind::ImportPath => {
+                self.stop_prompt();
+                self.import_path = text.trim().to_string();
+                self.import_file();
+            }
+        }
+    }
+
+    fn set_text_input(&self, on: bool) {
+        if let Ok(video) = self.sdl_context.video() {
+            if on {
+                video.text_input().start();
+            } else {
+                video.text_input().stop();
+            }
+        }
+    }
+
+    fn open_picker(&mut self) {
+        self.picker = Some(Picker {
+            query: String::new(),
+            results: search_banks(&self.banks, ""),
+            cursor: 0,
+        });
+        self.set_text_input(true);
+        self.refresh_title();
+    }
+
+    fn close_picker(&mut self) {
+        self.picker = None;
+        self.set_text_input(false);
+        self.refresh_title();
+    }
+
+    /// Input while the search popup is open. Returns true to quit, like
+    /// `do_input`.
+    fn picker_input(&mut self, event: Event) -> bool {
+        let Some(picker) = &mut self.picker else {
+            return false;
+        };
+        let mut query_changed = false;
+
+        match event {
+            Event::Quit { .. } => return true,
+            Event::TextInput { text, .. } => {
+                picker.query.push_str(&text);
+                query_changed = true;
+            }
+            Event::KeyDown { keycode: Some(Keycode::Backspace), .. } => {
+                query_changed = picker.query.pop().is_some();
+            }
+            Event::KeyDown { keycode: Some(Keycode::Down), .. } => {
+                picker.cursor = (picker.cursor + 1).min(picker.results.len().saturating_sub(1));
+            }
+            Event::KeyDown { keycode: Some(Keycode::Up), .. } => {
+                picker.cursor = picker.cursor.saturating_sub(1);
+            }
+            Event::KeyDown { keycode: Some(Keycode::Escape), .. } => {
+                self.close_picker();
+            }
+            Event::KeyDown { keycode: Some(Keycode::Return), .. } => {
+                if let Some(&(bank_idx, idx)) = picker.results.get(picker.cursor) {
+                    self.close_picker();
+                    self.bank_idx = bank_idx;
+                    self.strctr_selected = true;
+                    self.select_structure(idx);
+                }
+            }
+            _ => {}
+        }
+
+        if query_changed {
+            if let Some(picker) = &mut self.picker {
+                picker.results = search_banks(&self.banks, &picker.query);
+                picker.cursor = 0;
+            }
+            self.refresh_title();
+        }
+        false
+    }
+
+    /// Draws the search popup over the board, with the highlighted result
+    /// kept in view.
+    fn draw_picker(&mut self, texture_creator: &TextureCreator<WindowContext>, font: Option<&Font>) {
+        let Some(picker) = &self.picker else {
+            return;
+        };
+        let width = 520.min(self.tex_width);
+        let height = (SIDEBAR_HEADER_HEIGHT + SIDEBAR_ROW_HEIGHT * PICKER_ROWS as i32) as u32;
+        let popup = Rect::new(
+            (self.tex_width as i32 - width as i32) / 2,
+            (self.screen_height as i32 - height as i32) / 2,
+            width,
+            height,
+        );
+
+        let canvas = &mut self.canvas;
+        canvas.set_draw_color(self.color_panel);
+        let _ = canvas.fill_rect(popup);
+        canvas.set_draw_color(self.color_selection);
+        let _ = canvas.draw_rect(popup);
+
+        let text_x = popup.x() + 12 + THUMBNAIL_SIZE as i32;
+        let text_width = popup.width().saturating_sub(THUMBNAIL_SIZE + 20);
+        if let Some(font) = font {
+            let query = format!("Find: {}_", picker.query);
+            let _ = draw_text(canvas, texture_creator, font, &query, (popup.x() + 8, popup.y() + 8), popup.width() - 16, self.color_text);
+        }
+
+        let first = picker.cursor.saturating_sub(PICKER_ROWS - 1);
+        for (row, &(bank_idx, idx)) in picker.results.iter().enumerate().skip(first).take(PICKER_ROWS) {
+            let bank = &self.banks[bank_idx];
+            let y = popup.y() + SIDEBAR_HEADER_HEIGHT + (row - first) as i32 * SIDEBAR_ROW_HEIGHT;
+
+            if row == picker.cursor {
+                canvas.set_draw_color(self.color_highlight);
+                let _ = canvas.fill_rect(Rect::new(popup.x() + 1, y, popup.width() - 2, SIDEBAR_ROW_HEIGHT as u32));
+            }
+            if let Some(cells) = bank.cells(idx) {
+                let thumb = Rect::new(popup.x() + 6, y + 4, THUMBNAIL_SIZE, THUMBNAIL_SIZE);
+                draw_thumbnail(canvas, cells, thumb, self.color_alive, self.color_dead);
+            }
+            if let Some(font) = font {
+                let name = bank.nickname(idx).unwrap_or_default();
+                let detail = match bank.metadata(idx).map(|m| m.summary()) {
+                    Some(summary) if !summary.is_empty() => format!("{} - {}", bank.name(), summary),
+                    _ => bank.name().to_string(),
+                };
+                let _ = draw_text(canvas, texture_creator, font, name, (text_x, y + 8), text_width, self.color_text);
+                let _ = draw_text(canvas, texture_creator, font, &detail, (text_x, y + 27), text_width, self.color_text_dim);
+            }
+        }
+    }
+
+    /// Stamps the ghost at `origin`, or the whole array of it when array
+    /// placement is on.
+    fn stamp_ghost(&mut self, origin: (i32, i32)) {
+        match &self.array {
+            Some(array) => {
+                for (corner, cells) in array.copies(origin, &self.strctr_cursor) {
+                    self.symmetry.stamp(&mut self.board, self.symmetry_centre, corner, &cells, self.blend_mode);
+                }
+            }
+            None => self.symmetry.stamp(&mut self.board, self.symmetry_centre, origin, &self.strctr_cursor, self.blend_mode),
+        }
+    }
+
+    fn set_selection(&mut self, selection: Option<Rect>) {
+        self.selection = selection;
+        self.selection_mask = None;
+    }
+
+    /// Selects the live object under `cell`: its bounding box, masked so
+    /// only the object's own cells are copied or edited.
+    fn wand_select(&mut self, cell: (i32, i32)) {
+        let alive = self
+            .board
+            .wrap_coords(cell.0, cell.1)
+            .is_some_and(|(x, y)| self.board.cells[x as usize][y as usize]);
+        if !alive {
+            self.set_selection(None);
+            return;
+        }
+
+        let points = connected_cells(&self.board, cell, self.wand_reach);
+        let min_x = points.iter().map(|p| p.0).min().unwrap_or(cell.0);
+        let min_y = points.iter().map(|p| p.1).min().unwrap_or(cell.1);
+        let max_x = points.iter().map(|p| p.0).max().unwrap_or(cell.0);
+        let max_y = points.iter().map(|p| p.1).max().unwrap_or(cell.1);
+        self.set_selection(Some(rect_between((min_x, min_y), (max_x, max_y))));
+        self.selection_mask = Some(points.iter().filter_map(|&(x, y)| self.board.wrap_coords(x, y)).collect());
+        println!("selected {} cells", self.selection_mask.as_ref().map(|m| m.len()).unwrap_or(0));
+    }
+
+    /// Fills the dead area under `cell` with live cells, or clears the live
+    /// object there.
+    fn flood_fill(&mut self, cell: (i32, i32)) {
+        let Some((x, y)) = self.board.wrap_coords(cell.0, cell.1) else {
+            return;
+        };
+        let alive = self.board.cells[x as usize][y as usize];
+        let reach = if alive { self.wand_reach } else { 0 };
+        let points = connected_cells(&self.board, cell, reach);
+        self.draw_points(&points, !alive);
+    }
+
+    fn in_selection_mask(&self, x: i32, y: i32) -> bool {
+        self.selection_mask.as_ref().is_none_or(|mask| mask.contains(&(x, y)))
+    }
+
+    /// The selected cells as a `[y][x]` grid, leaving out anything the
+    /// magic wand didn't pick.
+    fn selection_grid(&self, region: Rect) -> Vec<Vec<u8>> {
+        // `region` is already clipped to the board.
+        let mut grid = board_to_grid(&self.board, Some(region)).unwrap_or_default();
+        for (y, row) in grid.iter_mut().enumerate() {
+            for (x, cell) in row.iter_mut().enumerate() {
+                if !self.in_selection_mask(region.x() + x as i32, region.y() + y as i32) {
+                    *cell = 0;
+                }
+            }
+        }
+        grid
+    }
+
+    /// The selection, cut down to the part on the board.
+    fn selection_region(&self) -> Option<Rect> {
+        self.selection.and_then(|s| clip_region(&self.board, Some(s)).ok())
+    }
+
+    fn copy_selection(&mut self, cut: bool) {
+        let Some(region) = self.selection_region() else {
+            println!("select a region with shift + drag first");
+            return;
+        };
+        let cells = self.selection_grid(region);
+        // Other programs get RLE; the copy kept here covers systems without
+        // a clipboard.
+        if let Err(e) = self.sdl_context.video().and_then(|v| v.clipboard().set_clipboard_text(&to_rle(&cells))) {
+            println!("could not set the clipboard: {}", e);
+        }
+        self.clipboard = Some(cells);
+        if cut {
+            self.checkpoint();
+            self.edit_selection(|_| false);
+        }
+        println!("{} {}x{} cells", if cut { "cut" } else { "copied" }, region.width(), region.height());
+    }
+
+    /// Picks up the clipboard as a ghost, in the orientation it was copied.
+    /// RLE or plaintext on the system clipboard wins over the last copy
+    /// made here.
+    fn paste_clipboard(&mut self) {
+        let text = self
+            .sdl_context
+            .video()
+            .ok()
+            .filter(|v| v.clipboard().has_clipboard_text())
+            .and_then(|v| v.clipboard().clipboard_text().ok());
+        let cells = match text.map(|t| parse_pattern(&t)) {
+            Some(Ok(cells)) => cells,
+            Some(Err(e)) if self.clipboard.is_none() => {
+                println!("could not paste: {}", e);
+                return;
+            }
+            _ => match self.clipboard.clone() {
+                Some(cells) => cells,
+                None => {
+                    println!("nothing copied yet");
+                    return;
+                }
+            },
+        };
+        self.strctr_orientation = Orientation::default();
+        self.set_ghost(cells);
+        self.strctr_selected = true;
+        self.set_selection(None);
+        self.refresh_title();
+    }
+
+    /// Sets every cell in the selection from its current state.
+    fn edit_selection(&mut self, edit: impl Fn(bool) -> bool) {
+        let Some(region) = self.selection_region() else {
+            return;
+        };
+        for x in region.x()..region.right() {
+            for y in region.y()..region.bottom() {
+                if !self.in_selection_mask(x, y) {
+                    continue;
+                }
+                let cell = &mut self.board.cells[x as usize][y as usize];
+                *cell = edit(*cell);
+            }
+        }
+    }
+
+    /// Applies a D8 transform to the selected cells in place, keeping the
+    /// top-left corner where it is.
+    fn transform_selection(&mut self, orientation: Orientation) {
+        let Some(region) = self.selection_region() else {
+            return;
+        };
+        let cells = orientation.apply(&self.selection_grid(region));
+        self.checkpoint();
+        self.edit_selection(|_| false);
+        // A wand selection shares its box with other cells, which must not
+        // be overwritten.
+        let mode = if self.selection_mask.is_some() { BlendMode::Or } else { BlendMode::Overwrite };
+        self.board.stamp((region.x(), region.y()), &cells, mode);
+
+        let (width, height) = (cells.first().map(|r| r.len()).unwrap_or(0), cells.len());
+        self.set_selection(Some(Rect::new(region.x(), region.y(), width as u32, height as u32)));
+    }
+
+    /// Turns or mirrors whatever is being edited: the held ghost if there
+    /// is one, otherwise the selection.
+    fn transform(&mut self, op: fn(Orientation) -> Orientation) {
+        if self.strctr_selected {
+            self.orient_ghost(op(self.strctr_orientation));
+        } else if self.selection.is_some() {
+            self.transform_selection(op(Orientation::default()));
+        }
+    }
+
+    /// Adds the live part of the selection to the active bank under `name`.
+    fn capture_selection(&mut self, name: &str) -> Result<(), String> {
+        let region = self.selection_region().ok_or("nothing selected on the board")?;
+        let cells = crop_to_live(&self.selection_grid(region))
+            .ok_or("the selection has no live cells")?;
+
+        let bank_idx = self.bank_idx;
+        self.banks[bank_idx].append_structure(name, cells)?;
+        if self.structures().path().is_none() {
+            // The placeholder bank has no file yet, give it one.
+            fs::create_dir_all(&self.bank_dir).map_err(|e| e.to_string())?;
+            let path = self.new_bank_path(self.structures().name());
+            self.banks[bank_idx].save_to_json(&path).map_err(|e| e.to_string())?;
+        }
+
+        println!("added \"{}\" to bank \"{}\"", name.trim(), self.structures().name());
+        self.strctr_idx = self.structures().len() - 1;
+        Ok(())
+    }
+
+    /// Reloads bank files that changed on disk and picks up new ones in the
+    /// bank directory. The selected entry is kept if it still exists.
+    fn reload_changed_banks(&mut self) {
+        self.last_bank_check = Instant::now();
+        let selected = self.structures().nickname(self.strctr_idx).map(str::to_string);
+        let active_path = self.structures().path().map(str::to_string);
+        let mut reloaded = false;
+
+        let mut idx = 0;
+        while idx < self.banks.len() {
+            if !self.banks[idx].changed_on_disk() {
+                idx += 1;
+                continue;
+            }
+            let path = self.banks[idx].path().unwrap_or_default().to_string();
+            if !Path::new(&path).exists() && self.banks.len() > 1 {
+                println!("bank file {} was removed", path);
+                self.banks.remove(idx);
+            } else {
+                match load_from_json(&path) {
+                    Ok(bank) => {
+                        println!("reloaded {}", path);
+                        self.banks[idx] = bank;
+                    }
+                    Err(issue) => {
+                        println!("could not reload {}: {}", path, issue);
+                        self.banks[idx].reload_failed(issue);
+                    }
+                }
+                idx += 1;
+            }
+            reloaded = true;
+        }
+
+        for path in bank_files(&self.bank_dir) {
+            let path = path.to_string_lossy().into_owned();
+            if !self.banks.iter().any(|b| b.path() == Some(path.as_str())) {
+                println!("found new bank {}", path);
+                self.banks.push(load_bank(&path));
+                reloaded = true;
+            }
+        }
+
+        if !reloaded {
+            return;
+        }
+
+        self.bank_idx = active_path
+            .and_then(|p| self.banks.iter().position(|b| b.path() == Some(p.as_str())))
+            .unwrap_or(0)
+            .min(self.banks.len() - 1);
+        match selected.and_then(|name| self.structures().find(&name)) {
+            Some(idx) => {
+                self.strctr_idx = idx;
+                if self.strctr_selected {
+                    if let Some(cells) = self.structures().get_cells(idx) {
+                        self.set_ghost(cells);
+                    }
+                }
+            }
+            None => self.strctr_idx = 0,
+        }
+        self.refresh_title();
+    }
+
+    /// The structure palette, in the space to the right of `tex_width`.
+    fn sidebar_rect(&self) -> Rect {
+        Rect::new(
+            self.tex_width as i32,
+            0,
+            self.screen_width.saturating_sub(self.tex_width).max(1),
+            self.screen_height,
+        )
+    }
+
+    fn in_sidebar(&self, x: i32, y: i32) -> bool {
+        self.show_sidebar && self.sidebar_rect().contains_point((x, y))
+    }
+
+    /// Height kept under the list for the selected entry's description
+    /// and url, or 0 if it has neither.
+    fn sidebar_detail_height(&self) -> i32 {
+        match self.structures().metadata(self.strctr_idx) {
+            Some(m) if m.description.is_some() || m.url.is_some() => SIDEBAR_DETAIL_HEIGHT,
+            _ => 0,
+        }
+    }
+
+    /// The bank entry drawn at screen position (x, y), if any.
+    fn sidebar_entry_at(&self, x: i32, y: i32) -> Option<usize> {
+        let list_bottom = self.sidebar_rect().bottom() - self.sidebar_detail_height();
+        if !self.in_sidebar(x, y) || y < SIDEBAR_HEADER_HEIGHT || y >= list_bottom {
+            return None;
+        }
+        let row = (y - SIDEBAR_HEADER_HEIGHT + self.sidebar_scroll) / SIDEBAR_ROW_HEIGHT;
+        self.structures().matching(&self.strctr_filter).get(row as usize).copied()
+    }
+
+    fn scroll_sidebar(&mut self, rows: i32) {
+        self.sidebar_scroll += rows * SIDEBAR_ROW_HEIGHT / 2;
+    }
+
+    /// Draws the palette straight onto the window, so exports and recordings
+    /// of `game_tex` never include it.
+    fn draw_sidebar(&mut self, texture_creator: &TextureCreator<WindowContext>, font: Option<&Font>) {
+        let panel = self.sidebar_rect();
+        let entries = self.structures().matching(&self.strctr_filter);
+        let detail_height = self.sidebar_detail_height();
+        let list_height = panel.height() as i32 - SIDEBAR_HEADER_HEIGHT - detail_height;
+        let max_scroll = (entries.len() as i32 * SIDEBAR_ROW_HEIGHT - list_height).max(0);
+        self.sidebar_scroll = self.sidebar_scroll.clamp(0, max_scroll);
+
+        let bank = &self.banks[self.bank_idx];
+        let canvas = &mut self.canvas;
+        canvas.set_draw_color(self.color_panel);
+        let _ = canvas.fill_rect(panel);
+
+        let text_x = panel.x() + 12 + THUMBNAIL_SIZE as i32;
+        let text_width = panel.width().saturating_sub(THUMBNAIL_SIZE + 20);
+        if let Some(font) = font {
+            let mut header = format!("{} ({})", bank.name(), entries.len());
+            if self.strctr_filter.is_active() {
+                header.push_str(&format!(" - {}", self.strctr_filter.describe()));
+            }
+            let _ = draw_text(canvas, texture_creator, font, &header, (panel.x() + 8, 8), panel.width() - 16, self.color_text);
+        }
+
+        let list = Rect::new(panel.x(), SIDEBAR_HEADER_HEIGHT, panel.width(), list_height.max(1) as u32);
+        canvas.set_clip_rect(list);
+        for (row, idx) in entries.iter().enumerate() {
+            let y = list.y() + row as i32 * SIDEBAR_ROW_HEIGHT - self.sidebar_scroll;
+            if y + SIDEBAR_ROW_HEIGHT < list.y() || y > list.bottom() {
+                continue;
+            }
+
+            if *idx == self.strctr_idx {
+                canvas.set_draw_color(self.color_highlight);
+                let _ = canvas.fill_rect(Rect::new(panel.x(), y, panel.width(), SIDEBAR_ROW_HEIGHT as u32));
+            }
+            if let Some(cells) = bank.cells(*idx) {
+                let thumb = Rect::new(panel.x() + 6, y + 4, THUMBNAIL_SIZE, THUMBNAIL_SIZE);
+                draw_thumbnail(canvas, cells, thumb, self.color_alive, self.color_dead);
+            }
+            if let Some(font) = font {
+                let name = bank.nickname(*idx).unwrap_or_default();
+                let summary = bank.metadata(*idx).map(|m| m.summary()).unwrap_or_default();
+                let _ = draw_text(canvas, texture_creator, font, name, (text_x, y + 8), text_width, self.color_text);
+                let _ = draw_text(canvas, texture_creator, font, &summary, (text_x, y + 27), text_width, self.color_text_dim);
+            }
+        }
+        canvas.set_clip_rect(None);
+
+        let (Some(font), Some(metadata)) = (font, bank.metadata(self.strctr_idx)) else {
+            return;
+        };
+        if detail_height == 0 {
+            return;
+        }
+        let detail = Rect::new(panel.x(), panel.bottom() - detail_height, panel.width(), detail_height as u32);
+        canvas.set_draw_color(self.color_highlight);
+        let _ = canvas.draw_line((detail.x(), detail.y()), (detail.right(), detail.y()));
+        canvas.set_clip_rect(detail);
+
+        let line_height = font.height();
+        let max_width = panel.width().saturating_sub(16);
+        let mut y = detail.y() + 6;
+        if let Some(description) = &metadata.description {
+            for line in wrap_text(font, description, max_width) {
+                // Leave the last line for the url.
+                if y + 2 * line_height > detail.bottom() && metadata.url.is_some() {
+                    break;
+                }
+                let _ = draw_text(canvas, texture_creator, font, &line, (panel.x() + 8, y), max_width, self.color_text);
+                y += line_height;
+            }
+        }
+        if let Some(url) = &metadata.url {
+            let _ = draw_text(canvas, texture_creator, font, url, (panel.x() + 8, y), max_width, self.color_text_dim);
+        }
+        canvas.set_clip_rect(None);
+    }
+
+    /// Moves the selection onto an entry matching the new filter.
+    fn apply_structure_filter(&mut self) {
+        match self.structures().first_matching(self.strctr_idx, &self.strctr_filter) {
+            Some(idx) => {
+                self.select_structure(idx);
+                println!("structure filter: {}", self.strctr_filter.describe());
+            }
+            None => {
+                println!("no structures match \"{}\"", self.strctr_filter.describe());
+                self.refresh_title();
+            }
+        }
+    }
+
+    /// Asks whether to pick up where the last run left off, if it left an
+    /// autosave behind.
+    pub fn offer_restore(&mut self) {
+        if !session_exists(&self.autosave_path) {
+            return;
+        }
+
+        let session = match load_session(&self.autosave_path) {
+            Ok(session) => session,
+            Err(e) => {
+                println!("could not read autosave \"{}\": {}", self.autosave_path, e);
+                return;
+            }
+        };
+
+        let buttons = [
+            ButtonData {
+                flags: MessageBoxButtonFlag::RETURNKEY_DEFAULT,
+                button_id: 1,
+                text: "Restore",
+            },
+            ButtonData {
+                flags: MessageBoxButtonFlag::ESCAPEKEY_DEFAULT,
+                button_id: 0,
+                text: "Discard",
+            },
+        ];
+        let message = format!(
+            "Restore the last session? (generation {}, {} live cells)",
+            session.generation,
+            session.live_cells.len()
+        );
+        let clicked = show_message_box(
+            MessageBoxFlag::INFORMATION,
+            &buttons,
+            "Game of Life",
+            &message,
+            self.canvas.window(),
+            None,
+        );
+
+        if let Ok(ClickedButton::CustomButton(ButtonData { button_id: 1, .. })) = clicked {
+            self.restore_session(session);
+        }
+    }
+
+    /// Writes the current session to the autosave file. Unless `blocking` is
+    /// set the write happens on a background thread, and is skipped while a
+    /// previous write is still in flight.
+    pub fn autosave(&mut self, blocking: bool) {
+        if let Some(job) = &self.autosave_job {
+            if !job.is_finished() && !blocking {
+                return;
+            }
+        }
+        if let Some(job) = self.autosave_job.take() {
+            if let Ok(Err(e)) = job.join() {
+                println!("autosave failed: {}", e);
+            }
+        }
+
+        let session = self.to_session();
+        let path = self.autosave_path.clone();
+        self.last_autosave = Instant::now();
+
+        if blocking {
+            if let Err(e) = save_session(&path, &session) {
+                println!("autosave failed: {}", e);
+            }
+        } else {
+            self.autosave_job = Some(thread::spawn(move || save_session(&path, &session)));
+        }
+    }
+
+    fn theme(&self) -> Theme {
+        Theme {
+            alive: self.color_alive,
+            dead: self.color_dead,
+            bg: self.color_bg,
+        }
+    }
+
+    /// The cells currently on screen, in board coordinates.
+    fn visible_region(&self) -> Rect {
+        let (x0, y0) = self.mouse_to_coords(0, 0);
+        let (x1, y1) = self.mouse_to_coords(self.screen_width as i32, self.screen_height as i32);
+        Rect::new(x0, y0, (x1 - x0 + 1).max(1) as u32, (y1 - y0 + 1).max(1) as u32)
+    }
+
+    /// Exports the board at `export_scale` pixels per cell, independent of
+    /// the window size and zoom level.
+    fn export_scaled_png(&self, region: Option<Rect>) -> Result<String, String> {
+        let mut image = rasterize(&self.board, region, self.export_scale, &self.theme())?;
+        let path = export_path("board", self.generation, "png");
+        image.save_png(&path)?;
+        Ok(path)
+    }
+
+    /// Exports pixels read back from `game_tex`, i.e. the board at screen
+    /// resolution.
+    fn export_screen_png(&self, pixels: Vec<u8>, rect: Rect) -> Result<String, String> {
+        let mut image = Image {
+            width: rect.width(),
+            height: rect.height(),
+            pixels,
+        };
+        let path = export_path("screen", self.generation, "png");
+        image.save_png(&path)?;
+        Ok(path)
+    }
+
+    /// Turns `import_path` into a ghost that's placed like a structure.
+    /// `.npy` and `.csv` grids are read as is, anything else as an image.
+    fn import_file(&mut self) {
+        let lower = self.import_path.to_lowercase();
+        let result = if lower.ends_with(".npy") {
+            read_npy(&self.import_path).map_err(|e| e.to_string())
+        } else if lower.ends_with(".csv") {
+            read_csv(&self.import_path).map_err(|e| e.to_string())
+        } else {
+            load_image_pattern(&self.import_path, self.import_settings)
+        };
+
+        match result {
+            Ok(cells) => {
+                println!(
+                    "imported {} as {}x{} cells",
+                    self.import_path,
+                    cells.first().map(|r| r.len()).unwrap_or(0),
+                    cells.len()
+                );
+                self.set_ghost(cells);
+                self.strctr_selected = true;
+            }
+            Err(e) => println!("could not import {}: {}", self.import_path, e),
+        }
+    }
 
-    sdl_context: Sdl,
-    canvas: Canvas<Window>,
-    tex_width: u32,
-    tex_height: u32,
-    tex_offset: i32,
-}
+    /// Loads a file dropped on the window. Patterns become a ghost to place,
+    /// or replace the board when shift or ctrl is held; structure `.json`
+    /// files become a new bank. Anything else goes through `import_file`.
+    fn drop_file(&mut self, path: &str) {
+        let lower = path.to_lowercase();
+        if lower.ends_with(".json") {
+            let bank = load_bank(path);
+            if bank.len() == 0 && !bank.issues().is_empty() {
+                println!("could not load {}:", path);
+                for issue in bank.issues() {
+                    println!("  {}", issue);
+                }
+                return;
+            }
+            if self.add_banks(vec![bank]) > 0 {
+                self.switch_bank(self.banks.len() - 1);
+            }
+            return;
+        }
 
-impl Game {
-    pub fn with_size(width: u32, height: u32) -> Self {
-        let sdl_context = sdl2::init().unwrap();
-        let video_subsystem = sdl_context.video().unwrap();
+        let text = if [".rle", ".cells", ".mc"].iter().any(|ext| lower.ends_with(ext)) {
+            match fs::read_to_string(path) {
+                Ok(text) => text,
+                Err(e) => {
+                    println!("could not read {}: {}", path, e);
+                    return;
+                }
+            }
+        } else {
+            self.import_path = path.to_string();
+            self.import_file();
+            return;
+        };
+        let result = if lower.ends_with(".mc") {
+            parse_macrocell(&text)
+        } else if lower.ends_with(".cells") {
+            parse_plaintext(&text)
+        } else {
+            parse_rle(&text)
+        };
+        let cells = match result {
+            Ok(cells) => cells,
+            Err(e) => {
+                println!("could not load {}: {}", path, e);
+                return;
+            }
+        };
 
-        let window = video_subsystem
-            .window("Game of Life", width, height)
-            .position_centered()
-            .build()
-            .unwrap();
+        let replace = self
+            .sdl_context
+            .keyboard()
+            .mod_state()
+            .intersects(Mod::LSHIFTMOD | Mod::RSHIFTMOD | Mod::LCTRLMOD | Mod::RCTRLMOD);
+        let (cols, rows) = (cells.first().map(|r| r.len()).unwrap_or(0) as i32, cells.len() as i32);
+        if replace {
+            self.checkpoint();
+            self.board.reset();
+            self.generation = 0;
+            let origin = ((self.board.width - cols) / 2, (self.board.height - rows) / 2);
+            self.board.stamp(origin, &cells, BlendMode::Overwrite);
+            println!("loaded {} ({}x{}) onto the board", path, cols, rows);
+        } else {
+            self.strctr_orientation = Orientation::default();
+            self.set_ghost(cells);
+            self.strctr_selected = true;
+            self.set_selection(None);
+            println!("loaded {} ({}x{})", path, cols, rows);
+        }
+        self.refresh_title();
+    }
 
-        let mut canvas = window.into_canvas().accelerated().build().unwrap();
-        canvas.set_blend_mode(sdl2::render::BlendMode::Blend);
+    fn export_svg(&self, region: Option<Rect>, grid_lines: bool) {
+        let svg = match board_to_svg(&self.board, region, self.export_scale, &self.theme(), grid_lines) {
+            Ok(svg) => svg,
+            Err(e) => {
+                println!("export failed: {}", e);
+                return;
+            }
+        };
+        let path = export_path("board", self.generation, "svg");
+        match write_svg(&path, &svg) {
+            Ok(()) => println!("exported {}", path),
+            Err(e) => println!("export failed: {}", e),
+        }
+    }
 
-        let cells_width = 300;
-        let cells_height = 300;
-        let cell_width = 12;
-        let cell_height = 12;
+    fn export_grid(&self, csv: bool, region: Option<Rect>) {
+        let grid = match board_to_grid(&self.board, region) {
+            Ok(grid) => grid,
+            Err(e) => {
+                println!("export failed: {}", e);
+                return;
+            }
+        };
+        let (path, result) = if csv {
+            let path = export_path("board", self.generation, "csv");
+            let result = write_csv(&path, &grid);
+            (path, result)
+        } else {
+            let path = export_path("board", self.generation, "npy");
+            let result = write_npy(&path, &grid);
+            (path, result)
+        };
 
-        let tex_offset = 0;
-        let tex_width = 1000;
-        let tex_height = height - tex_offset as u32 * 2;
+        match result {
+            Ok(()) => println!("exported {}", path),
+            Err(e) => println!("export failed: {}", e),
+        }
+    }
 
-        Game {
-            board: Board::with_size(cells_width, cells_height),
-            color_alive: Color::RGB(0x17, 0x17, 0x17),
-            color_dead: Color::RGB(0xF7, 0xF7, 0xF7),
-            color_bg: Color::RGB(0x7F, 0x7F, 0x7F),
-            color_ghost_alive: Color::RGBA(0, 0, 0xFF, 0x8F),
-            color_ghost_dead: Color::RGBA(0, 0, 0xFF, 0x2F),
-            color_cursor: Color::RGBA(0xFF, 0, 0, 0x7F),
-            dark_mode: false,
-            screen_width: width,
-            screen_height: height,
-            cell_width: 12,
-            cell_height: 12,
-            generation: 0,
-            pan_cam: false,
-            cam_offset_x: 40,
-            cam_offset_y: 40,
-            move_dir: None,
+    fn toggle_recording(&mut self, keymod: Mod) {
+        if let Some(recorder) = self.recorder.take() {
+            println!("recording stopped, {} frames written", recorder.frames());
+            return;
+        }
 
-            structures: load_from_json("structures.json".to_string()).unwrap(),
-            strctr_selected: false,
-            strctr_idx: 0,
-            strctr_cursor: vec![],
+        self.record_settings.format = if keymod.intersects(Mod::LSHIFTMOD | Mod::RSHIFTMOD) {
+            RecordFormat::PngSequence
+        } else {
+            RecordFormat::Gif
+        };
+        self.record_settings.region = if keymod.intersects(Mod::LCTRLMOD | Mod::RCTRLMOD) {
+            Some(self.visible_region())
+        } else {
+            None
+        };
+        self.record_settings.scale = self.export_scale;
+        self.record_each_frame = keymod.intersects(Mod::LALTMOD | Mod::RALTMOD);
 
-            cursor_rect: Rect::new(0, 0, cell_width as u32, cell_height as u32),
-            last_selected: None,
+        let path = match self.record_settings.format {
+            RecordFormat::Gif => export_path("recording", self.generation, "gif"),
+            RecordFormat::PngSequence => export_path("recording", self.generation, "frames"),
+        };
+        match Recorder::start(&path, self.record_settings, self.theme(), &self.board) {
+            Ok(recorder) => {
+                println!("recording to {}", path);
+                self.recorder = Some(recorder);
+                self.last_recorded_gen = None;
+            }
+            Err(e) => println!("could not start recording: {}", e),
+        }
+    }
 
-            run_sim: false,
-            denom: 60,
+    fn record_frame(&mut self) {
+        if !self.record_each_frame && self.last_recorded_gen == Some(self.generation) {
+            return;
+        }
+        if let Some(recorder) = &mut self.recorder {
+            if let Err(e) = recorder.capture(&self.board) {
+                println!("recording stopped: {}", e);
+                self.recorder = None;
+            }
+            self.last_recorded_gen = Some(self.generation);
+        }
+    }
 
-            sdl_context,
-            canvas,
-            tex_width,
-            tex_height,
-            tex_offset,
+    /// Converts a region in cell coordinates to the on-screen pixels it
+    /// covers.
+    fn screen_rect_for(&self, region: Option<Rect>) -> Option<Rect> {
+        let screen = Rect::new(0, 0, self.screen_width, self.screen_height);
+        match region {
+            Some(r) => Rect::new(
+                self.cam_offset_x + r.x() * self.cell_width,
+                self.cam_offset_y + r.y() * self.cell_height,
+                r.width() * self.cell_width as u32,
+                r.height() * self.cell_height as u32,
+            )
+            .intersection(screen),
+            None => Some(screen),
         }
     }
 
@@ -128,79 +1515,106 @@ impl Game {
         (array_x, array_y)
     }
 
-    fn plot_line_low(&mut self, pt_1: (i32, i32), pt_2: (i32, i32)) {
-        let (x0, y0) = pt_1;
-        let (x1, y1) = pt_2;
-        let dx = x1 - x0;
-        let mut dy = y1 - y0;
-        let mut yi = 1i32;
-        if dy < 0 {
-            yi = -1;
-            dy = -dy;
-        }
-        let mut d = (dy * 2) - dx;
-        let mut y = y0;
+    /// Freehand stroke segment between two mouse samples, painting the
+    /// brush at every cell along the way.
+    fn plot_line(&mut self, pt_1: (i32, i32), pt_2: (i32, i32)) {
         if let Some(status) = self.last_selected {
-            for x in x0..x1 {
-                self.board.cells[x as usize][y as usize] = status;
-                if d > 0 {
-                    y += yi;
-                    d += 2 * (dy - dx);
-                } else {
-                    d += dy * 2;
-                }
+            for point in line_points(pt_1, pt_2) {
+                self.paint_brush(point, status);
             }
         }
     }
 
-    fn plot_line_high(&mut self, pt_1: (i32, i32), pt_2: (i32, i32)) {
-        let (x0, y0) = pt_1;
-        let (x1, y1) = pt_2;
-        let mut dx = x1 - x0;
-        let dy = y1 - y0;
-        let mut xi = 1i32;
-        if dx < 0 {
-            xi = -1;
-            dx = -dx;
+    fn paint_brush(&mut self, centre: (i32, i32), alive: bool) {
+        let mut points = self.brush.footprint(centre);
+        if self.brush.spray {
+            let chance = self.brush.spray_chance;
+            points.retain(|_| rand::random::<f64>() < chance);
         }
-        let mut d = (dx * 2) - dy;
-        let mut x = x0;
-        if let Some(status) = self.last_selected {
-            for y in y0..y1 {
-                self.board.cells[x as usize][y as usize] = status;
-                if d > 0 {
-                    x += xi;
-                    d += 2 * (dx - dy);
-                } else {
-                    d += dx * 2;
-                }
-            }
+        self.draw_points(&points, alive);
+    }
+
+    fn change_brush(&mut self, change: impl FnOnce(&mut Brush)) {
+        change(&mut self.brush);
+        println!("{}", self.brush.describe());
+        self.refresh_title();
+    }
+
+    fn set_tool(&mut self, tool: Tool) {
+        self.tool = tool;
+        self.shape_start = None;
+        println!("tool: {}", tool.label());
+        self.refresh_title();
+    }
+
+    /// Saves the board for undo. Call just before changing it.
+    fn checkpoint(&mut self) {
+        self.history.record(&self.board, self.generation);
+    }
+
+    fn undo(&mut self) {
+        if self.history.undo(&mut self.board, &mut self.generation) {
+            println!("undone ({} more to undo)", self.history.undo_len());
+        } else {
+            println!("nothing to undo");
         }
     }
 
-    fn plot_line(&mut self, pt_1: (i32, i32), pt_2: (i32, i32)) {
-        let (x0, y0) = pt_1;
-        let (x1, y1) = pt_2;
-        if (y1 - y0).abs() < (x1 - x0).abs() {
-            if x0 > x1 {
-                self.plot_line_low(pt_2, pt_1);
-            } else {
-                self.plot_line_low(pt_1, pt_2);
-            }
+    fn redo(&mut self) {
+        if self.history.redo(&mut self.board, &mut self.generation) {
+            println!("redone ({} more to redo)", self.history.redo_len());
         } else {
-            if y0 > y1 {
-                self.plot_line_high(pt_2, pt_1);
-            } else {
-                self.plot_line_high(pt_1, pt_2);
+            println!("nothing to redo");
+        }
+    }
+
+    /// Sets cells, and their images under the drawing symmetry.
+    fn draw_points(&mut self, points: &[(i32, i32)], alive: bool) {
+        for &point in points {
+            for image in self.symmetry.images(self.symmetry_centre, point) {
+                self.board.blend_cell(image, alive, BlendMode::Overwrite);
             }
         }
     }
 
+    /// Screen lines along the symmetry's mirror axes, or a cross marking the
+    /// centre for the rotations.
+    fn symmetry_axes(&self) -> Vec<(Point, Point)> {
+        let cx = self.cam_offset_x + self.symmetry_centre.0 * self.cell_width / 2;
+        let cy = self.cam_offset_y + self.symmetry_centre.1 * self.cell_height / 2;
+        let reach = (self.screen_width + self.screen_height) as i32;
+        let vertical = (Point::new(cx, cy - reach), Point::new(cx, cy + reach));
+        let horizontal = (Point::new(cx - reach, cy), Point::new(cx + reach, cy));
+        // Diagonals follow the cells, which may not be square on screen.
+        let (dx, dy) = (reach, reach * self.cell_height / self.cell_width.max(1));
+        let diagonal = (Point::new(cx - dx, cy - dy), Point::new(cx + dx, cy + dy));
+        let anti_diagonal = (Point::new(cx - dx, cy + dy), Point::new(cx + dx, cy - dy));
+        let marker = self.cell_width.max(self.cell_height);
+        let cross = vec![
+            (Point::new(cx - marker, cy), Point::new(cx + marker, cy)),
+            (Point::new(cx, cy - marker), Point::new(cx, cy + marker)),
+        ];
+
+        match self.symmetry {
+            Symmetry::None => vec![],
+            Symmetry::Horizontal => vec![vertical],
+            Symmetry::Vertical => vec![horizontal],
+            Symmetry::Diagonal => vec![diagonal],
+            Symmetry::Rotate2 | Symmetry::Rotate4 => cross,
+            Symmetry::D4 => vec![vertical, horizontal],
+            Symmetry::D8 => vec![vertical, horizontal, diagonal, anti_diagonal],
+        }
+    }
+
+    /// The range of cell sizes, in pixels, that zooming allows.
+    pub const MIN_CELL_SIZE: i32 = 4;
+    pub const MAX_CELL_SIZE: i32 = 60;
+
     fn zoom_in_out(&mut self, zoom_in: bool, keymod: Option<Mod>, mouse_pos: Option<(i32, i32)>) {
         let old_width = self.cell_width;
         let old_height = self.cell_height;
-        let max_dim = 60;
-        let min_dim = 4;
+        let max_dim = Self::MAX_CELL_SIZE;
+        let min_dim = Self::MIN_CELL_SIZE;
 
         let mut delta_length = match keymod {
             Some(km) if km.intersects(Mod::LSHIFTMOD | Mod::RSHIFTMOD) => 6,
@@ -235,8 +1649,17 @@ impl Game {
 
 
         let (mut cursor_x, mut cursor_y) = (0, 0);
+        let mut cursor_on_board = false;
 
         let texture_creator = self.canvas.texture_creator();
+        let ttf_context = sdl2::ttf::init().map_err(|e| println!("could not start SDL_ttf: {}", e)).ok();
+        let font = match (&ttf_context, find_font()) {
+            (Some(ttf), Some(path)) => ttf.load_font(path, FONT_SIZE).map_err(|e| println!("{}: {}", path, e)).ok(),
+            _ => {
+                println!("no font found, the structure palette will show thumbnails only");
+                None
+            }
+        };
         let mut game_tex = texture_creator
             .create_texture(
                 None,
@@ -290,6 +1713,26 @@ impl Game {
                 }
             });
 
+            if let Some((kind, region)) = self.pending_export.take() {
+                let result = match kind {
+                    ExportKind::Scaled => self.export_scaled_png(region),
+                    ExportKind::Screen => match self.screen_rect_for(region) {
+                        Some(rect) => {
+                            let mut pixels = Err("could not read game texture".to_string());
+                            let _ = self.canvas.with_texture_canvas(&mut game_tex, |tc| {
+                                pixels = tc.read_pixels(rect, PixelFormatEnum::RGBA32);
+                            });
+                            pixels.and_then(|pixels| self.export_screen_png(pixels, rect))
+                        }
+                        None => Err("export region is off screen".to_string()),
+                    },
+                };
+                match result {
+                    Ok(path) => println!("exported {}", path),
+                    Err(e) => println!("export failed: {}", e),
+                }
+            }
+
             let mouse_x = event_pump.mouse_state().x() - self.tex_offset;
             let mouse_y = event_pump.mouse_state().y() - self.tex_offset;
 
@@ -300,17 +1743,25 @@ impl Game {
             }
 
             let (old_cx, old_cy) = (cursor_x, cursor_y);
+            let was_on_board = cursor_on_board;
             (cursor_x, cursor_y) = self.mouse_to_coords(mouse_x, mouse_y);
-            if let Some(held_status) = self.last_selected {
-                if cursor_x >= 0 && (cursor_x as usize) < self.board.cells.len() && cursor_y >= 0 && (cursor_y as usize) < self.board.cells[0].len() {
-                    if self.board.cells[cursor_x as usize][cursor_y as usize] != held_status {
-                        if (old_cx, old_cy) != (cursor_x, cursor_y) {
-                            self.plot_line((old_cx, old_cy), (cursor_x, cursor_y));
-                        } else {
-                            self.board.cells[cursor_x as usize][cursor_y as usize] = held_status;
-                        }
-                    }
-                }
+            cursor_on_board = self.board.contains(cursor_x, cursor_y)
+                && !self.in_sidebar(mouse_x + self.tex_offset, mouse_y + self.tex_offset);
+            // Spray keeps landing while the mouse is held still; a solid
+            // brush only needs repainting when it moves. Strokes stop at the
+            // board's edge, and pick up again where the cursor comes back
+            // on rather than joining across the gap; only the brush
+            // footprint wraps.
+            if self.last_selected.is_some()
+                && cursor_on_board
+                && ((old_cx, old_cy) != (cursor_x, cursor_y) || self.brush.spray)
+            {
+                let from = if was_on_board { (old_cx, old_cy) } else { (cursor_x, cursor_y) };
+                self.plot_line(from, (cursor_x, cursor_y));
+            }
+
+            if let Some(anchor) = self.select_anchor {
+                self.set_selection(Some(rect_between(anchor, (cursor_x, cursor_y))));
             }
 
             let cursor_rect_x = (cursor_x * self.cell_width) + self.cam_offset_x;
@@ -318,6 +1769,27 @@ impl Game {
             self.cursor_rect.x = cursor_rect_x;
             self.cursor_rect.y = cursor_rect_y;
 
+            let (ghost_alive, ghost_dead) = self.ghost_colors();
+            let shape_preview: Vec<(i32, i32)> = match self.shape_start {
+                Some(start) => self
+                    .tool
+                    .points(start, (cursor_x, cursor_y))
+                    .into_iter()
+                    .flat_map(|p| self.symmetry.images(self.symmetry_centre, p))
+                    .filter_map(|(x, y)| self.board.wrap_coords(x, y))
+                    .collect(),
+                None => vec![],
+            };
+            let symmetry_axes = self.symmetry_axes();
+            let shape_color = if self.shape_alive {
+                self.color_ghost_alive
+            } else {
+                Color::RGBA(0xE0, 0x20, 0x20, 0x8F)
+            };
+            let ghost_origins = match &self.array {
+                Some(array) => array.origins((cursor_x, cursor_y)),
+                None => vec![(cursor_x, cursor_y)],
+            };
             let _ = self.canvas.with_texture_canvas(&mut game_tex, |tc| {
                 if self.move_dir.is_some() {
                     match self.move_dir {
@@ -333,31 +1805,70 @@ impl Game {
                     self.cam_offset_x += new_mouse_x - mouse_x;
                     self.cam_offset_y += new_mouse_y - mouse_y;
                 } else if self.strctr_selected {
-                    let mut ghost_rect = Rect::new(
-                        cursor_rect_x,
-                        cursor_rect_y,
-                        self.cell_width as u32,
-                        self.cell_height as u32,
-                    );
+                    // Drawn where each cell will actually land, so the part
+                    // hanging off an edge shows up on the far side of a
+                    // wrapping board and disappears on a bounded one. Array
+                    // copies are previewed unevolved.
+                    for (origin_x, origin_y) in &ghost_origins {
+                        for (y_offset, row) in self.strctr_cursor.iter().enumerate() {
+                            for (x_offset, col) in row.iter().enumerate() {
+                                let Some((x, y)) = self
+                                    .board
+                                    .wrap_coords(origin_x + x_offset as i32, origin_y + y_offset as i32)
+                                else {
+                                    continue;
+                                };
+                                match (*col, ghost_dead) {
+                                    (0, Some(color)) => tc.set_draw_color(color),
+                                    (0, None) => continue,
+                                    _ => tc.set_draw_color(ghost_alive),
+                                }
 
-                    for row in &self.strctr_cursor {
-                        for col in row {
-                            match *col {
-                                0 => tc.set_draw_color(self.color_ghost_dead),
-                                1 => tc.set_draw_color(self.color_ghost_alive),
-                                _ => unreachable!("Bad value in structure array"),
+                                let _ = tc.fill_rect(Rect::new(
+                                    x * self.cell_width + self.cam_offset_x,
+                                    y * self.cell_height + self.cam_offset_y,
+                                    self.cell_width as u32,
+                                    self.cell_height as u32,
+                                ));
                             }
-
-                            let _ = tc.fill_rect(ghost_rect);
-                            ghost_rect.x += self.cell_width;
                         }
-                        ghost_rect.y += self.cell_height;
-                        ghost_rect.x = (cursor_x * self.cell_width) + self.cam_offset_x;
                     }
                 } else {
                     tc.set_draw_color(self.color_cursor);
                     let _ = tc.fill_rect(self.cursor_rect);
                 }
+
+                tc.set_draw_color(shape_color);
+                for (x, y) in &shape_preview {
+                    let _ = tc.fill_rect(Rect::new(
+                        x * self.cell_width + self.cam_offset_x,
+                        y * self.cell_height + self.cam_offset_y,
+                        self.cell_width as u32,
+                        self.cell_height as u32,
+                    ));
+                }
+
+                tc.set_draw_color(self.color_selection);
+                for (from, to) in &symmetry_axes {
+                    let _ = tc.draw_line(*from, *to);
+                }
+
+                if let Some(selection) = self.selection {
+                    let sel_rect = Rect::new(
+                        self.cam_offset_x + selection.x() * self.cell_width,
+                        self.cam_offset_y + selection.y() * self.cell_height,
+                        selection.width() * self.cell_width as u32,
+                        selection.height() * self.cell_height as u32,
+                    );
+                    tc.set_draw_color(self.color_selection);
+                    let _ = tc.draw_rect(sel_rect);
+                    let _ = tc.draw_rect(Rect::new(
+                        sel_rect.x() + 1,
+                        sel_rect.y() + 1,
+                        sel_rect.width().saturating_sub(2),
+                        sel_rect.height().saturating_sub(2),
+                    ));
+                }
             });
 
             let draw_rect = Rect::new(
@@ -365,19 +1876,43 @@ impl Game {
             );
             let _ = self.canvas.copy(&game_tex, None, draw_rect);
 
+            if self.show_sidebar {
+                self.draw_sidebar(&texture_creator, font.as_ref());
+            }
+            self.draw_orientation(&texture_creator, font.as_ref(), (mouse_x + self.tex_offset, mouse_y + self.tex_offset));
+            self.draw_picker(&texture_creator, font.as_ref());
+
             if self.run_sim {
                 self.board.step_game();
                 self.generation += 1;
             }
 
+            self.record_frame();
+
+            if self.last_bank_check.elapsed() >= Duration::from_secs(1) {
+                self.reload_changed_banks();
+            }
+
+            if self.last_autosave.elapsed() >= self.autosave_interval {
+                self.autosave(false);
+            }
+
             self.canvas.present();
             ::std::thread::sleep(Duration::new(0, 1_000_000_000u32 / self.denom as u32));
         }
 
+        self.autosave(true);
         Ok(())
     }
 
     fn do_input(&mut self, event: Event, mouse_pos: (i32, i32)) -> bool {
+        if self.prompt.is_some() {
+            return self.prompt_input(event);
+        }
+        if self.picker.is_some() {
+            return self.picker_input(event);
+        }
+
         match event {
             Event::Quit { .. }
             | Event::KeyDown {
@@ -391,9 +1926,19 @@ impl Game {
                     self.run_sim = !self.run_sim;
                 }
                 Some(Keycode::R) => {
+                    self.checkpoint();
                     self.board.reset();
                     self.generation = 0;
                 }
+                Some(Keycode::Z) if keymod.intersects(Mod::LCTRLMOD | Mod::RCTRLMOD) && keymod.intersects(Mod::LSHIFTMOD | Mod::RSHIFTMOD) => {
+                    self.redo();
+                }
+                Some(Keycode::Z) if keymod.intersects(Mod::LCTRLMOD | Mod::RCTRLMOD) => {
+                    self.undo();
+                }
+                Some(Keycode::Y) if keymod.intersects(Mod::LCTRLMOD | Mod::RCTRLMOD) => {
+                    self.redo();
+                }
                 Some(Keycode::Z) => {
                     self.cam_offset_x = 0;
                     self.cam_offset_y = 0;
@@ -430,40 +1975,110 @@ impl Game {
                     self.cursor_rect.w = self.cell_width;
                     self.cursor_rect.h = self.cell_height;
                 }
+                Some(Keycode::S) if keymod.intersects(Mod::LCTRLMOD | Mod::RCTRLMOD) => {
+                    self.start_naming();
+                }
                 Some(Keycode::S) => {
                     if !self.strctr_selected {
-                        self.strctr_selected = true;
-                        self.strctr_cursor = self.structures.get_cells(self.strctr_idx);
+                        if let Some(idx) = self.structures().first_matching(self.strctr_idx, &self.strctr_filter) {
+                            self.strctr_selected = true;
+                            self.select_structure(idx);
+                        }
                     } else {
                         self.strctr_selected = !self.strctr_selected;
                     }
                 }
                 Some(Keycode::D) => {
                     if self.strctr_selected {
-                        self.strctr_idx += 1;
-                        if self.strctr_idx > self.structures.len() {
-                            self.strctr_idx = self.structures.len();
+                        if let Some(idx) = self.structures().step_matching(self.strctr_idx, &self.strctr_filter, true) {
+                            self.select_structure(idx);
                         }
-                        self.strctr_cursor = self.structures.get_cells(self.strctr_idx);
                     }
                 }
+                Some(Keycode::A) if keymod.intersects(Mod::LCTRLMOD | Mod::RCTRLMOD) => {
+                    self.start_prompt(PromptKind::ArraySettings, String::new());
+                }
                 Some(Keycode::A) => {
                     if self.strctr_selected {
-                        self.strctr_idx = self.strctr_idx.saturating_sub(1);
-                        self.strctr_cursor = self.structures.get_cells(self.strctr_idx);
+                        if let Some(idx) = self.structures().step_matching(self.strctr_idx, &self.strctr_filter, false) {
+                            self.select_structure(idx);
+                        }
                     }
                 }
-                Some(Keycode::F) => {
-                    if self.strctr_selected {
-                        for row in &mut self.strctr_cursor {
-                            row.reverse();
-                        }
+                Some(Keycode::B) => {
+                    let ctrl = keymod.intersects(Mod::LCTRLMOD | Mod::RCTRLMOD);
+                    let shift = keymod.intersects(Mod::LSHIFTMOD | Mod::RSHIFTMOD);
+                    match (ctrl, shift) {
+                        (true, false) => self.merge_with_next_bank(),
+                        (true, true) => self.split_bank(),
+                        (false, true) => self.switch_bank(self.bank_idx + self.banks.len() - 1),
+                        (false, false) => self.switch_bank(self.bank_idx + 1),
                     }
                 }
-                Some(Keycode::E) => {
-                    if self.strctr_selected {
-                        self.strctr_cursor.reverse();
+                Some(Keycode::Tab) => {
+                    if keymod.intersects(Mod::LSHIFTMOD | Mod::RSHIFTMOD) {
+                        let rules = self.structures().rules();
+                        let next = match &self.strctr_filter.rule {
+                            None => rules.first(),
+                            Some(rule) => rules
+                                .iter()
+                                .position(|r| r == rule)
+                                .and_then(|i| rules.get(i + 1)),
+                        };
+                        self.strctr_filter.rule = next.cloned();
+                    } else {
+                        let next = match self.strctr_filter.category {
+                            None => Category::ALL.first(),
+                            Some(category) => Category::ALL
+                                .iter()
+                                .position(|c| *c == category)
+                                .and_then(|i| Category::ALL.get(i + 1)),
+                        };
+                        self.strctr_filter.category = next.copied();
                     }
+                    self.apply_structure_filter();
+                }
+                Some(Keycode::F) if keymod.intersects(Mod::LCTRLMOD | Mod::RCTRLMOD) => {
+                    self.open_picker();
+                }
+                Some(Keycode::F) => {
+                    self.transform(Orientation::flipped_horizontal);
+                }
+                Some(Keycode::E) => {
+                    self.transform(Orientation::flipped_vertical);
+                }
+                Some(Keycode::W) => {
+                    self.transform(Orientation::rotated_cw);
+                }
+                Some(Keycode::Q) => {
+                    self.transform(Orientation::rotated_ccw);
+                }
+                Some(Keycode::X) if keymod.intersects(Mod::LCTRLMOD | Mod::RCTRLMOD) => {
+                    self.copy_selection(true);
+                }
+                Some(Keycode::X) => {
+                    self.transform(Orientation::transposed);
+                }
+                Some(Keycode::C) if keymod.intersects(Mod::LCTRLMOD | Mod::RCTRLMOD) => {
+                    self.copy_selection(false);
+                }
+                Some(Keycode::V) if keymod.intersects(Mod::LCTRLMOD | Mod::RCTRLMOD) => {
+                    self.paste_clipboard();
+                }
+                Some(Keycode::Delete) | Some(Keycode::Backspace) => {
+                    self.checkpoint();
+                    self.edit_selection(|_| false);
+                }
+                Some(Keycode::Insert) => {
+                    self.checkpoint();
+                    self.edit_selection(|_| true);
+                }
+                Some(Keycode::I) if keymod.intersects(Mod::LCTRLMOD | Mod::RCTRLMOD) => {
+                    self.checkpoint();
+                    self.edit_selection(|cell| !cell);
+                }
+                Some(Keycode::O) => {
+                    self.orient_ghost(Orientation::default());
                 }
                 Some(Keycode::H) => {
                     self.move_dir = Some(MoveDir::LEFT);
@@ -477,27 +2092,170 @@ impl Game {
                 Some(Keycode::L) => {
                     self.move_dir = Some(MoveDir::RIGHT);
                 }
+                Some(Keycode::V) if self.selection.is_some() => {
+                    self.checkpoint();
+                    self.edit_selection(|_| rand::random());
+                }
                 Some(Keycode::V) => {
+                    self.checkpoint();
                     self.board.randomize();
                 }
-                Some(Keycode::C) => {
-                    self.dark_mode = !self.dark_mode;
-                    if self.dark_mode {
-                        self.color_alive = Color::RGB(0xA7, 0xA7, 0xA7);
-                        self.color_dead = Color::RGB(0x17, 0x17, 0x17);
-                        self.color_bg = Color::RGB(0x27, 0x27, 0x27);
-                        self.color_ghost_alive = Color::RGBA(0, 0x96, 0xFF, 0x7F);
-                        self.color_ghost_dead = Color::RGBA(0, 0x96, 0xFF, 0x3F);
-                        self.color_cursor = Color::RGBA(0xFF, 0, 0, 0x7F);
+                Some(Keycode::P) => {
+                    let region = if keymod.intersects(Mod::LCTRLMOD | Mod::RCTRLMOD) {
+                        Some(self.visible_region())
+                    } else {
+                        None
+                    };
+                    if keymod.intersects(Mod::LALTMOD | Mod::RALTMOD) {
+                        self.export_svg(region, keymod.intersects(Mod::LSHIFTMOD | Mod::RSHIFTMOD));
+                    } else {
+                        let kind = if keymod.intersects(Mod::LSHIFTMOD | Mod::RSHIFTMOD) {
+                            ExportKind::Scaled
+                        } else {
+                            ExportKind::Screen
+                        };
+                        self.pending_export = Some((kind, region));
+                    }
+                }
+                Some(Keycode::I) => {
+                    if keymod.intersects(Mod::LSHIFTMOD | Mod::RSHIFTMOD) {
+                        self.import_settings.dither = !self.import_settings.dither;
+                        println!("image import dithering: {}", self.import_settings.dither);
                     } else {
-                        self.color_alive = Color::RGB(0x17, 0x17, 0x17);
-                        self.color_dead = Color::RGB(0xF7, 0xF7, 0xF7);
-                        self.color_bg = Color::RGB(0x7F, 0x7F, 0x7F);
-                        self.color_ghost_alive = Color::RGBA(0, 0, 0xFF, 0x8F);
-                        self.color_ghost_dead = Color::RGBA(0, 0, 0xFF, 0x2F);
-                        self.color_cursor = Color::RGBA(0xFF, 0, 0, 0x7F);
+                        self.start_prompt(PromptKind::ImportPath, self.import_path.clone());
                     }
                 }
+                Some(Keycode::Num1) => {
+                    self.set_tool(Tool::Freehand);
+                }
+                Some(Keycode::Num2) => {
+                    self.set_tool(Tool::Line);
+                }
+                Some(Keycode::Num3) => {
+                    self.set_tool(Tool::Rect);
+                }
+                Some(Keycode::Num4) => {
+                    self.set_tool(Tool::FilledRect);
+                }
+                Some(Keycode::Num5) => {
+                    self.set_tool(Tool::Ellipse);
+                }
+                Some(Keycode::Num6) => {
+                    self.set_tool(Tool::FilledEllipse);
+                }
+                Some(Keycode::Num7) => {
+                    self.set_tool(Tool::Fill);
+                }
+                Some(Keycode::Num8) if self.tool == Tool::Wand => {
+                    self.wand_reach = self.wand_reach % 4 + 1;
+                    println!("magic wand reach: {} cell(s)", self.wand_reach);
+                }
+                Some(Keycode::Num8) => {
+                    self.set_tool(Tool::Wand);
+                }
+                Some(Keycode::Num9) if keymod.intersects(Mod::LSHIFTMOD | Mod::RSHIFTMOD) => {
+                    self.import_settings.threshold = self.import_settings.threshold.saturating_sub(16);
+                    println!("image import threshold: {}", self.import_settings.threshold);
+                }
+                Some(Keycode::Num0) if keymod.intersects(Mod::LSHIFTMOD | Mod::RSHIFTMOD) => {
+                    self.import_settings.threshold = self.import_settings.threshold.saturating_add(16);
+                    println!("image import threshold: {}", self.import_settings.threshold);
+                }
+                Some(Keycode::Num9) => {
+                    self.import_settings.px_per_cell = (self.import_settings.px_per_cell - 1).max(1);
+                    println!("image import: {} px per cell", self.import_settings.px_per_cell);
+                }
+                Some(Keycode::Num0) => {
+                    self.import_settings.px_per_cell = (self.import_settings.px_per_cell + 1).min(32);
+                    println!("image import: {} px per cell", self.import_settings.px_per_cell);
+                }
+                Some(Keycode::N) => {
+                    let region = if keymod.intersects(Mod::LCTRLMOD | Mod::RCTRLMOD) {
+                        Some(self.visible_region())
+                    } else {
+                        None
+                    };
+                    self.export_grid(keymod.intersects(Mod::LSHIFTMOD | Mod::RSHIFTMOD), region);
+                }
+                Some(Keycode::U) => {
+                    self.sticky_stamp = !self.sticky_stamp;
+                    println!("repeat stamp {}", if self.sticky_stamp { "on" } else { "off" });
+                    self.refresh_title();
+                }
+                Some(Keycode::Y) if keymod.intersects(Mod::LSHIFTMOD | Mod::RSHIFTMOD) || keymod.intersects(Mod::LALTMOD | Mod::RALTMOD) => {
+                    let (x, y) = self.mouse_to_coords(mouse_pos.0, mouse_pos.1);
+                    // Alt puts the centre on the cell's top-left corner,
+                    // for patterns with an even width.
+                    self.symmetry_centre = if keymod.intersects(Mod::LALTMOD | Mod::RALTMOD) { (2 * x, 2 * y) } else { (2 * x + 1, 2 * y + 1) };
+                    println!(
+                        "symmetry centre: ({}, {})",
+                        self.symmetry_centre.0 as f32 / 2.0,
+                        self.symmetry_centre.1 as f32 / 2.0
+                    );
+                }
+                Some(Keycode::Y) => {
+                    self.symmetry = self.symmetry.next();
+                    println!("symmetry: {}", self.symmetry.label());
+                    self.refresh_title();
+                }
+                Some(Keycode::M) => {
+                    self.blend_mode = self.blend_mode.next();
+                    println!("paste mode: {}", self.blend_mode.label());
+                    self.refresh_title();
+                }
+                Some(Keycode::T) if keymod.intersects(Mod::LCTRLMOD | Mod::RCTRLMOD) => {
+                    self.board.wrap = !self.board.wrap;
+                    println!("board edges {}", if self.board.wrap { "wrap around" } else { "are bounded" });
+                }
+                Some(Keycode::T) => {
+                    self.show_sidebar = !self.show_sidebar;
+                }
+                Some(Keycode::G) => {
+                    self.toggle_recording(keymod);
+                }
+                Some(Keycode::Comma) => {
+                    self.record_settings.frame_skip = self.record_settings.frame_skip.saturating_sub(1);
+                    println!("recording frame skip: {}", self.record_settings.frame_skip);
+                }
+                Some(Keycode::Period) => {
+                    self.record_settings.frame_skip += 1;
+                    println!("recording frame skip: {}", self.record_settings.frame_skip);
+                }
+                Some(Keycode::Semicolon) if keymod.intersects(Mod::LSHIFTMOD | Mod::RSHIFTMOD) => {
+                    self.change_brush(|b| b.spray_chance = (b.spray_chance - 0.05).max(0.05));
+                }
+                Some(Keycode::Quote) if keymod.intersects(Mod::LSHIFTMOD | Mod::RSHIFTMOD) => {
+                    self.change_brush(|b| b.spray_chance = (b.spray_chance + 0.05).min(1.0));
+                }
+                Some(Keycode::Semicolon) => {
+                    self.change_brush(|b| b.radius = (b.radius - 1).max(1));
+                }
+                Some(Keycode::Quote) => {
+                    self.change_brush(|b| b.radius = (b.radius + 1).min(MAX_BRUSH_RADIUS));
+                }
+                Some(Keycode::Backslash) => {
+                    self.change_brush(|b| {
+                        b.shape = match b.shape {
+                            BrushShape::Square => BrushShape::Round,
+                            BrushShape::Round => BrushShape::Square,
+                        }
+                    });
+                }
+                Some(Keycode::Slash) => {
+                    self.change_brush(|b| b.spray = !b.spray);
+                }
+                Some(Keycode::LeftBracket) => {
+                    self.export_scale = (self.export_scale - 1).max(1);
+                    println!("export scale: {} px per cell", self.export_scale);
+                }
+                Some(Keycode::RightBracket) => {
+                    self.export_scale = (self.export_scale + 1).min(64);
+                    println!("export scale: {} px per cell", self.export_scale);
+                }
+                Some(Keycode::C) => {
+                    self.dark_mode = !self.dark_mode;
+                    self.apply_theme();
+                }
                 _ => {}
             },
             Event::KeyUp {
@@ -508,6 +2266,14 @@ impl Game {
                 },
                 _ => {},
             },
+            Event::MouseButtonDown {
+                x, y, mouse_btn, ..
+            } if self.in_sidebar(x, y) => {
+                if let (MouseButton::Left, Some(idx)) = (mouse_btn, self.sidebar_entry_at(x, y)) {
+                    self.strctr_selected = true;
+                    self.select_structure(idx);
+                }
+            }
             Event::MouseButtonDown {
                 x, y, mouse_btn, ..
             } => match mouse_btn {
@@ -517,32 +2283,41 @@ impl Game {
                         y - self.tex_offset,
                     );
 
-                    if self.strctr_selected {
-                        if (0..self.board.width).contains(&new_x)
-                            && (0..self.board.height).contains(&new_y)
-                        {
-                            for (y_offset, row) in self.strctr_cursor.iter().enumerate() {
-                                for (x_offset, col) in row.iter().enumerate() {
-                                    let status = match *col {
-                                        0 => false,
-                                        1 => true,
-                                        _ => panic!("Bad value in structure array"),
-                                    };
-                                    self.board.cells[x_offset + new_x as usize]
-                                        [y_offset + new_y as usize] = status;
-                                }
+                    let keymod = self.sdl_context.keyboard().mod_state();
+                    let shift = keymod.intersects(Mod::LSHIFTMOD | Mod::RSHIFTMOD);
+
+                    if shift && !self.strctr_selected {
+                        self.select_anchor = Some((new_x, new_y));
+                        self.set_selection(Some(rect_between((new_x, new_y), (new_x, new_y))));
+                    } else if self.strctr_selected {
+                        // Only the stamped cells wrap; the click itself
+                        // has to land on the board.
+                        if self.board.contains(new_x, new_y) {
+                            self.checkpoint();
+                            self.stamp_ghost((new_x, new_y));
+
+                            if !self.sticky_stamp {
+                                self.strctr_selected = false;
                             }
                         }
-
-                        self.strctr_selected = false;
-                    } else if !self.pan_cam
-                        && (0..self.board.width).contains(&new_x)
-                        && (0..self.board.height).contains(&new_y)
-                    {
-                        if self.last_selected == None {
+                    } else if self.tool == Tool::Wand {
+                        self.wand_select((new_x, new_y));
+                    } else if self.selection.is_some() {
+                        self.set_selection(None);
+                    } else if !self.pan_cam && self.board.contains(new_x, new_y) {
+                        if self.tool == Tool::Fill {
+                            self.checkpoint();
+                            self.flood_fill((new_x, new_y));
+                        } else if self.tool != Tool::Freehand {
+                            self.shape_start = Some((new_x, new_y));
+                            self.shape_alive = !keymod.intersects(Mod::LCTRLMOD | Mod::RCTRLMOD);
+                        } else if self.last_selected == None {
                             let cell_status = self.board.cells[new_x as usize][new_y as usize];
                             self.last_selected = Some(!cell_status);
-                            self.board.cells[new_x as usize][new_y as usize] = !cell_status;
+                            // The whole stroke, up to the button coming
+                            // back up, is one undo step.
+                            self.checkpoint();
+                            self.paint_brush((new_x, new_y), !cell_status);
                         }
                     }
                 }
@@ -551,10 +2326,16 @@ impl Game {
                 }
                 _ => {}
             },
-            Event::MouseButtonUp { mouse_btn, .. } => {
+            Event::MouseButtonUp { x, y, mouse_btn, .. } => {
                 match mouse_btn {
                     MouseButton::Left => {
                         self.last_selected = None;
+                        self.select_anchor = None;
+                        if let Some(start) = self.shape_start.take() {
+                            let end = self.mouse_to_coords(x - self.tex_offset, y - self.tex_offset);
+                            self.checkpoint();
+                            self.draw_points(&self.tool.points(start, end), self.shape_alive);
+                        }
                     },
                     MouseButton::Right => {
                         self.pan_cam = false;
@@ -562,6 +2343,12 @@ impl Game {
                     _ => {}
                 }
             }
+            Event::DropFile { filename, .. } => {
+                self.drop_file(&filename);
+            }
+            Event::MouseWheel { y, .. } if self.in_sidebar(mouse_pos.0, mouse_pos.1) => {
+                self.scroll_sidebar(-y);
+            }
             Event::MouseWheel { y, .. } => {
                 match y {
                     y if y > 0 => self.zoom_in_out(true, None, Some(mouse_pos)),
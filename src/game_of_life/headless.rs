@@ -0,0 +1,139 @@
+use sdl2::rect::Rect;
+
+use crate::game_of_life::board::Board;
+use crate::game_of_life::export::Theme;
+use crate::game_of_life::interchange::NpyStackWriter;
+use crate::game_of_life::recording::*;
+use crate::game_of_life::parse_value;
+use crate::game_of_life::session::{load_session, MAX_BOARD_SIZE};
+
+pub struct HeadlessOptions {
+    pub width: i32,
+    pub height: i32,
+    pub generations: u64,
+    pub session: Option<String>,
+    pub dark_mode: bool,
+    pub record: Option<String>,
+    pub record_settings: RecordSettings,
+    pub npy_stack: Option<String>,
+}
+
+impl Default for HeadlessOptions {
+    fn default() -> Self {
+        HeadlessOptions {
+            width: 300,
+            height: 300,
+            generations: 100,
+            session: None,
+            dark_mode: false,
+            record: None,
+            record_settings: RecordSettings::default(),
+            npy_stack: None,
+        }
+    }
+}
+
+fn parse_region(value: Option<&String>) -> Result<Rect, String> {
+    let parts: Vec<i32> = parse_value::<String>("--region", value)?
+        .split(',')
+        .map(|p| p.trim().parse())
+        .collect::<Result<_, _>>()
+        .map_err(|_| "--region expects x,y,w,h".to_string())?;
+
+    match parts[..] {
+        [x, y, w, h] if w > 0 && h > 0 => Ok(Rect::new(x, y, w as u32, h as u32)),
+        _ => Err("--region expects x,y,w,h".to_string()),
+    }
+}
+
+/// Parses the arguments following `--headless`.
+pub fn parse_args(args: &[String]) -> Result<HeadlessOptions, String> {
+    let mut opts = HeadlessOptions::default();
+    let mut args = args.iter();
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--width" => opts.width = parse_value(arg, args.next())?,
+            "--height" => opts.height = parse_value(arg, args.next())?,
+            "--generations" => opts.generations = parse_value(arg, args.next())?,
+            "--session" => opts.session = Some(parse_value(arg, args.next())?),
+            "--dark" => opts.dark_mode = true,
+            "--record" => {
+                opts.record = Some(parse_value(arg, args.next())?);
+                opts.record_settings.format = RecordFormat::Gif;
+            }
+            "--record-png" => {
+                opts.record = Some(parse_value(arg, args.next())?);
+                opts.record_settings.format = RecordFormat::PngSequence;
+            }
+            "--npy-stack" => opts.npy_stack = Some(parse_value(arg, args.next())?),
+            "--scale" => opts.record_settings.scale = parse_value(arg, args.next())?,
+            "--skip" => opts.record_settings.frame_skip = parse_value(arg, args.next())?,
+            "--delay" => opts.record_settings.delay = parse_value(arg, args.next())?,
+            "--region" => opts.record_settings.region = Some(parse_region(args.next())?),
+            _ => return Err(format!("unknown headless option \"{}\"", arg)),
+        }
+    }
+
+    let board_sizes = 1..=MAX_BOARD_SIZE;
+    if !board_sizes.contains(&opts.width) || !board_sizes.contains(&opts.height) {
+        return Err(format!("--width and --height must be between 1 and {}", MAX_BOARD_SIZE));
+    }
+    // Large scales are caught by `Recorder::start`, before any frame is drawn.
+    if opts.record_settings.scale == 0 {
+        return Err("--scale must be at least 1".to_string());
+    }
+
+    Ok(opts)
+}
+
+/// Runs the simulation without opening a window. The board comes from a
+/// session file if given, otherwise it starts as random soup.
+pub fn run_headless(opts: HeadlessOptions) -> Result<(), String> {
+    let mut board = match &opts.session {
+        Some(path) => load_session(path).map_err(|e| format!("{}: {}", path, e))?.to_board(),
+        None => {
+            let mut board = Board::with_size(opts.width, opts.height);
+            board.randomize();
+            board
+        }
+    };
+    let theme = if opts.dark_mode { Theme::dark() } else { Theme::light() };
+
+    let mut recorder = match &opts.record {
+        Some(path) => Some(Recorder::start(path, opts.record_settings, theme, &board)?),
+        None => None,
+    };
+
+    // The stack shares the recording's region but keeps every generation.
+    let mut stack = match &opts.npy_stack {
+        Some(path) => Some(
+            NpyStackWriter::create(path, &board, opts.record_settings.region)
+                .map_err(|e| format!("{}: {}", path, e))?,
+        ),
+        None => None,
+    };
+
+    for generation in 0..=opts.generations {
+        if let Some(recorder) = &mut recorder {
+            recorder.capture(&board)?;
+        }
+        if let Some(stack) = &mut stack {
+            stack.push(&board).map_err(|e| e.to_string())?;
+        }
+        if generation < opts.generations {
+            board.step_game();
+        }
+    }
+
+    if let Some(recorder) = &recorder {
+        println!("recorded {} frames to {}", recorder.frames(), opts.record.unwrap_or_default());
+    }
+    if let Some(stack) = stack {
+        let frames = stack.finish().map_err(|e| e.to_string())?;
+        println!("wrote {} generations to {}", frames, opts.npy_stack.unwrap_or_default());
+    }
+
+    println!("ran {} generations, {} live cells", opts.generations, board.live_cells().len());
+    Ok(())
+}
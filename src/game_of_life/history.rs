@@ -0,0 +1,195 @@
+use std::{collections::VecDeque, mem};
+
+use crate::game_of_life::board::Board;
+
+/// The board as it was before an edit.
+struct Snapshot {
+    live: Vec<(i32, i32)>,
+    generation: u64,
+}
+
+impl Snapshot {
+    fn take(board: &Board, generation: u64) -> Self {
+        Snapshot {
+            live: board.live_cells(),
+            generation,
+        }
+    }
+
+    fn bytes(&self) -> usize {
+        mem::size_of::<Self>() + self.live.len() * mem::size_of::<(i32, i32)>()
+    }
+}
+
+/// Undo and redo stacks of whole-board snapshots. Undoing goes back to the
+/// board as it was just before the edit, generation included. The oldest
+/// steps are dropped once the stacks take up more than `max_bytes`.
+pub struct History {
+    undo: VecDeque<Snapshot>,
+    redo: Vec<Snapshot>,
+    bytes: usize,
+    max_bytes: usize,
+}
+
+impl History {
+    pub fn new(max_bytes: usize) -> Self {
+        History {
+            undo: VecDeque::new(),
+            redo: vec![],
+            bytes: 0,
+            max_bytes,
+        }
+    }
+
+    /// Call before changing the board. Clears the redo stack.
+    pub fn record(&mut self, board: &Board, generation: u64) {
+        let snapshot = Snapshot::take(board, generation);
+        // Nothing has changed since the last step, e.g. a clear with
+        // nothing selected, so there is nothing new to undo back to.
+        if self
+            .undo
+            .back()
+            .is_some_and(|last| last.generation == generation && last.live == snapshot.live)
+        {
+            return;
+        }
+
+        for dropped in self.redo.drain(..) {
+            self.bytes -= dropped.bytes();
+        }
+        self.bytes += snapshot.bytes();
+        self.undo.push_back(snapshot);
+
+        while self.bytes > self.max_bytes && self.undo.len() > 1 {
+            if let Some(dropped) = self.undo.pop_front() {
+                self.bytes -= dropped.bytes();
+            }
+        }
+    }
+
+    /// Puts the board back to before the last edit. Returns false if there
+    /// is nothing to undo.
+    pub fn undo(&mut self, board: &mut Board, generation: &mut u64) -> bool {
+        let Some(snapshot) = self.undo.pop_back() else {
+            return false;
+        };
+        let current = Snapshot::take(board, *generation);
+        self.bytes += current.bytes();
+        self.redo.push(current);
+        self.restore(snapshot, board, generation);
+        true
+    }
+
+    pub fn redo(&mut self, board: &mut Board, generation: &mut u64) -> bool {
+        let Some(snapshot) = self.redo.pop() else {
+            return false;
+        };
+        let current = Snapshot::take(board, *generation);
+        self.bytes += current.bytes();
+        self.undo.push_back(current);
+        self.restore(snapshot, board, generation);
+        true
+    }
+
+    fn restore(&mut self, snapshot: Snapshot, board: &mut Board, generation: &mut u64) {
+        self.bytes -= snapshot.bytes();
+        board.set_live_cells(&snapshot.live);
+        *generation = snapshot.generation;
+    }
+
+    pub fn undo_len(&self) -> usize {
+        self.undo.len()
+    }
+
+    pub fn redo_len(&self) -> usize {
+        self.redo.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn board_with(live: &[(i32, i32)]) -> Board {
+        let mut board = Board::with_size(8, 8);
+        board.set_live_cells(live);
+        board
+    }
+
+    #[test]
+    fn undo_and_redo_restore_cells_and_generation() {
+        let mut history = History::new(usize::MAX);
+        let mut board = board_with(&[(1, 1)]);
+        let mut generation = 3;
+
+        history.record(&board, generation);
+        board.set_live_cells(&[(2, 2), (3, 3)]);
+        generation = 7;
+
+        assert!(history.undo(&mut board, &mut generation));
+        assert_eq!(board.live_cells(), vec![(1, 1)]);
+        assert_eq!(generation, 3);
+
+        assert!(history.redo(&mut board, &mut generation));
+        assert_eq!(board.live_cells(), vec![(2, 2), (3, 3)]);
+        assert_eq!(generation, 7);
+
+        assert!(!history.redo(&mut board, &mut generation));
+    }
+
+    #[test]
+    fn unchanged_board_is_not_recorded_twice() {
+        let mut history = History::new(usize::MAX);
+        let board = board_with(&[(1, 1)]);
+
+        history.record(&board, 0);
+        history.record(&board, 0);
+        assert_eq!(history.undo_len(), 1);
+
+        // A different generation is a different step.
+        history.record(&board, 1);
+        assert_eq!(history.undo_len(), 2);
+    }
+
+    #[test]
+    fn new_edit_clears_redo() {
+        let mut history = History::new(usize::MAX);
+        let mut board = board_with(&[(1, 1)]);
+        let mut generation = 0;
+
+        history.record(&board, generation);
+        board.set_live_cells(&[(2, 2)]);
+        history.undo(&mut board, &mut generation);
+        assert_eq!(history.redo_len(), 1);
+
+        history.record(&board, generation);
+        assert_eq!(history.redo_len(), 0);
+        assert_eq!(history.bytes, history.undo.iter().map(Snapshot::bytes).sum::<usize>());
+    }
+
+    #[test]
+    fn oldest_steps_are_dropped_over_the_cap() {
+        let step_bytes = Snapshot::take(&board_with(&[(0, 0)]), 0).bytes();
+        let mut history = History::new(step_bytes * 3);
+
+        for x in 0..8 {
+            history.record(&board_with(&[(x, 0)]), 0);
+        }
+        assert_eq!(history.undo_len(), 3);
+        assert!(history.bytes <= step_bytes * 3);
+
+        // The oldest steps went, so undoing ends at the sixth board.
+        let mut board = board_with(&[]);
+        let mut generation = 0;
+        while history.undo(&mut board, &mut generation) {}
+        assert_eq!(board.live_cells(), vec![(5, 0)]);
+    }
+
+    #[test]
+    fn keeps_the_latest_step_even_if_it_is_over_the_cap() {
+        let mut history = History::new(0);
+        history.record(&board_with(&[(1, 1)]), 0);
+        history.record(&board_with(&[(2, 2)]), 0);
+        assert_eq!(history.undo_len(), 1);
+    }
+}
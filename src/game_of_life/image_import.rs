@@ -0,0 +1,110 @@
+use sdl2::{image::LoadSurface, pixels::PixelFormatEnum, surface::Surface};
+
+#[derive(Clone, Copy)]
+pub struct ImportSettings {
+    /// Luminance below which a pixel becomes a live cell.
+    pub threshold: u8,
+    pub dither: bool,
+    /// Image pixels averaged into each cell.
+    pub px_per_cell: u32,
+}
+
+impl Default for ImportSettings {
+    fn default() -> Self {
+        ImportSettings {
+            threshold: 128,
+            dither: false,
+            px_per_cell: 1,
+        }
+    }
+}
+
+/// Loads a PNG or JPEG and turns it into a cell pattern, laid out the same
+/// way as a structure's `cells` (rows of 0/1). Dark pixels become live.
+pub fn load_image_pattern(path: &str, settings: ImportSettings) -> Result<Vec<Vec<u8>>, String> {
+    let surface = Surface::from_file(path)?.convert_format(PixelFormatEnum::RGBA32)?;
+    let luma = downsample(&surface, settings.px_per_cell.max(1));
+
+    Ok(if settings.dither {
+        dither(luma, settings.threshold)
+    } else {
+        threshold(&luma, settings.threshold)
+    })
+}
+
+/// Averages blocks of `px_per_cell` x `px_per_cell` pixels into one
+/// luminance value per cell.
+fn downsample(surface: &Surface, px_per_cell: u32) -> Vec<Vec<f32>> {
+    let width = surface.width();
+    let height = surface.height();
+    let pitch = surface.pitch() as usize;
+    let rows = height.div_ceil(px_per_cell) as usize;
+    let cols = width.div_ceil(px_per_cell) as usize;
+
+    surface.with_lock(|pixels| {
+        let mut luma = vec![vec![0f32; cols]; rows];
+        let mut counts = vec![vec![0u32; cols]; rows];
+
+        for y in 0..height as usize {
+            for x in 0..width as usize {
+                let idx = y * pitch + x * 4;
+                let (r, g, b, a) = (pixels[idx], pixels[idx + 1], pixels[idx + 2], pixels[idx + 3]);
+                // Transparent pixels read as white, i.e. dead.
+                let alpha = a as f32 / 255.0;
+                let value = 0.2126 * r as f32 + 0.7152 * g as f32 + 0.0722 * b as f32;
+                let value = value * alpha + 255.0 * (1.0 - alpha);
+
+                let row = y / px_per_cell as usize;
+                let col = x / px_per_cell as usize;
+                luma[row][col] += value;
+                counts[row][col] += 1;
+            }
+        }
+
+        for (luma_row, count_row) in luma.iter_mut().zip(&counts) {
+            for (value, count) in luma_row.iter_mut().zip(count_row) {
+                *value /= (*count).max(1) as f32;
+            }
+        }
+
+        luma
+    })
+}
+
+fn threshold(luma: &[Vec<f32>], level: u8) -> Vec<Vec<u8>> {
+    luma.iter()
+        .map(|row| row.iter().map(|v| (*v < level as f32) as u8).collect())
+        .collect()
+}
+
+/// Floyd–Steinberg error diffusion.
+fn dither(mut luma: Vec<Vec<f32>>, level: u8) -> Vec<Vec<u8>> {
+    let rows = luma.len();
+    let cols = luma.first().map(|r| r.len()).unwrap_or(0);
+    let mut cells = vec![vec![0u8; cols]; rows];
+
+    for y in 0..rows {
+        for x in 0..cols {
+            let old = luma[y][x];
+            let alive = old < level as f32;
+            let new = if alive { 0.0 } else { 255.0 };
+            cells[y][x] = alive as u8;
+
+            let err = old - new;
+            if x + 1 < cols {
+                luma[y][x + 1] += err * 7.0 / 16.0;
+            }
+            if y + 1 < rows {
+                if x > 0 {
+                    luma[y + 1][x - 1] += err * 3.0 / 16.0;
+                }
+                luma[y + 1][x] += err * 5.0 / 16.0;
+                if x + 1 < cols {
+                    luma[y + 1][x + 1] += err / 16.0;
+                }
+            }
+        }
+    }
+
+    cells
+}
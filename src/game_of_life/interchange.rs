@@ -0,0 +1,204 @@
+use std::{
+    fs::{self, File},
+    io::{self, BufWriter, Read, Seek, SeekFrom, Write},
+};
+
+use sdl2::rect::Rect;
+
+use crate::game_of_life::board::Board;
+use crate::game_of_life::export::clip_region;
+
+const NPY_MAGIC: &[u8] = b"\x93NUMPY";
+// Room for the largest shape tuple we write, so the header of a stack can be
+// rewritten in place once the frame count is known.
+const NPY_HEADER_LEN: usize = 128;
+
+fn invalid(msg: impl Into<String>) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg.into())
+}
+
+/// Copies a region of the board into rows of 0/1, indexed `[y][x]` like a
+/// structure's `cells`.
+pub fn board_to_grid(board: &Board, region: Option<Rect>) -> Result<Vec<Vec<u8>>, String> {
+    let region = clip_region(board, region)?;
+    Ok((region.y()..region.y() + region.height() as i32)
+        .map(|y| {
+            (region.x()..region.x() + region.width() as i32)
+                .map(|x| board.cells[x as usize][y as usize] as u8)
+                .collect()
+        })
+        .collect())
+}
+
+fn npy_header(shape: &[usize]) -> Vec<u8> {
+    let dims: Vec<String> = shape.iter().map(|d| d.to_string()).collect();
+    let shape = if dims.len() == 1 {
+        format!("({},)", dims[0])
+    } else {
+        format!("({})", dims.join(", "))
+    };
+    let mut dict = format!("{{'descr': '|u1', 'fortran_order': False, 'shape': {}, }}", shape);
+
+    let prefix_len = NPY_MAGIC.len() + 4;
+    while (prefix_len + dict.len() + 1) < NPY_HEADER_LEN {
+        dict.push(' ');
+    }
+    dict.push('\n');
+
+    let mut header = NPY_MAGIC.to_vec();
+    header.extend_from_slice(&[1, 0]);
+    header.extend_from_slice(&(dict.len() as u16).to_le_bytes());
+    header.extend_from_slice(dict.as_bytes());
+    header
+}
+
+/// Writes a 2-D uint8 `.npy` array of shape (rows, cols).
+pub fn write_npy(path: &str, grid: &[Vec<u8>]) -> io::Result<()> {
+    let rows = grid.len();
+    let cols = grid.first().map(|r| r.len()).unwrap_or(0);
+
+    let mut out = BufWriter::new(File::create(path)?);
+    out.write_all(&npy_header(&[rows, cols]))?;
+    for row in grid {
+        out.write_all(row)?;
+    }
+    out.flush()
+}
+
+/// Reads a uint8 or bool `.npy` array. 2-D arrays are taken as is; for a 3-D
+/// stack the last frame is used.
+pub fn read_npy(path: &str) -> io::Result<Vec<Vec<u8>>> {
+    let mut bytes = vec![];
+    File::open(path)?.read_to_end(&mut bytes)?;
+
+    if bytes.len() < 10 || &bytes[..6] != NPY_MAGIC {
+        return Err(invalid("not an .npy file"));
+    }
+    let (header_len, header_start) = match bytes[6] {
+        1 => (u16::from_le_bytes([bytes[8], bytes[9]]) as usize, 10),
+        2 | 3 if bytes.len() >= 12 => (
+            u32::from_le_bytes([bytes[8], bytes[9], bytes[10], bytes[11]]) as usize,
+            12,
+        ),
+        v => return Err(invalid(format!("unsupported .npy version {}", v))),
+    };
+    let data_start = header_start + header_len;
+    let header = bytes
+        .get(header_start..data_start)
+        .map(String::from_utf8_lossy)
+        .ok_or_else(|| invalid("truncated .npy header"))?;
+
+    if !["'|u1'", "'<u1'", "'|b1'", "'|i1'"].iter().any(|d| header.contains(d)) {
+        return Err(invalid("only uint8/bool .npy arrays are supported"));
+    }
+    if header.contains("'fortran_order': True") {
+        return Err(invalid("fortran-ordered .npy arrays are not supported"));
+    }
+
+    let shape_start = header.find("'shape':").ok_or_else(|| invalid("missing shape"))?;
+    let shape_str = &header[shape_start..];
+    let open = shape_str.find('(').ok_or_else(|| invalid("bad shape"))?;
+    let close = shape_str.find(')').ok_or_else(|| invalid("bad shape"))?;
+    let shape: Vec<usize> = shape_str[open + 1..close]
+        .split(',')
+        .map(str::trim)
+        .filter(|d| !d.is_empty())
+        .map(|d| d.parse().map_err(|_| invalid("bad shape")))
+        .collect::<io::Result<_>>()?;
+
+    let (frames, rows, cols) = match shape[..] {
+        [rows, cols] => (1, rows, cols),
+        [frames, rows, cols] if frames > 0 => (frames, rows, cols),
+        _ => return Err(invalid("expected a 2-D array or a 3-D stack")),
+    };
+    // The shape comes from the file, so it can claim more than is there.
+    let frame_len = rows.checked_mul(cols).ok_or_else(|| invalid("bad shape"))?;
+    let last_frame = (frames - 1)
+        .checked_mul(frame_len)
+        .and_then(|offset| offset.checked_add(data_start))
+        .ok_or_else(|| invalid("bad shape"))?;
+    let data_end = last_frame
+        .checked_add(frame_len)
+        .filter(|end| *end <= bytes.len())
+        .ok_or_else(|| invalid("truncated .npy data"))?;
+    let data = &bytes[last_frame..data_end];
+
+    Ok(data.chunks(cols.max(1)).map(|row| row.iter().map(|v| (*v != 0) as u8).collect()).collect())
+}
+
+pub fn write_csv(path: &str, grid: &[Vec<u8>]) -> io::Result<()> {
+    let mut out = BufWriter::new(File::create(path)?);
+    for row in grid {
+        let line: Vec<String> = row.iter().map(|v| v.to_string()).collect();
+        writeln!(out, "{}", line.join(","))?;
+    }
+    out.flush()
+}
+
+/// Reads a CSV grid; any non-zero value is a live cell. Short rows are
+/// padded with dead cells.
+pub fn read_csv(path: &str) -> io::Result<Vec<Vec<u8>>> {
+    let contents = fs::read_to_string(path)?;
+    let mut grid: Vec<Vec<u8>> = vec![];
+
+    for (line_no, line) in contents.lines().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        let row = line
+            .split(',')
+            .map(|v| {
+                v.trim()
+                    .parse::<f64>()
+                    .map(|v| (v != 0.0) as u8)
+                    .map_err(|_| invalid(format!("line {}: bad value \"{}\"", line_no + 1, v.trim())))
+            })
+            .collect::<io::Result<Vec<u8>>>()?;
+        grid.push(row);
+    }
+
+    let cols = grid.iter().map(|r| r.len()).max().unwrap_or(0);
+    for row in &mut grid {
+        row.resize(cols, 0);
+    }
+
+    Ok(grid)
+}
+
+/// Streams generations into a 3-D uint8 `.npy` of shape (frames, rows, cols).
+/// The frame count in the header is patched in by `finish`.
+pub struct NpyStackWriter {
+    out: BufWriter<File>,
+    region: Rect,
+    frames: usize,
+}
+
+impl NpyStackWriter {
+    pub fn create(path: &str, board: &Board, region: Option<Rect>) -> io::Result<Self> {
+        let region = clip_region(board, region).map_err(invalid)?;
+        let mut out = BufWriter::new(File::create(path)?);
+        out.write_all(&npy_header(&[0, region.height() as usize, region.width() as usize]))?;
+
+        Ok(NpyStackWriter { out, region, frames: 0 })
+    }
+
+    pub fn push(&mut self, board: &Board) -> io::Result<()> {
+        for row in board_to_grid(board, Some(self.region)).map_err(invalid)? {
+            self.out.write_all(&row)?;
+        }
+        self.frames += 1;
+        Ok(())
+    }
+
+    pub fn finish(mut self) -> io::Result<usize> {
+        let header = npy_header(&[
+            self.frames,
+            self.region.height() as usize,
+            self.region.width() as usize,
+        ]);
+        self.out.seek(SeekFrom::Start(0))?;
+        self.out.write_all(&header)?;
+        self.out.flush()?;
+        Ok(self.frames)
+    }
+}
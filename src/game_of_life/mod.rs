@@ -1,10 +1,50 @@
 mod board;
+mod export;
 mod game;
+mod headless;
+mod history;
+mod image_import;
+mod interchange;
+mod placement;
+mod recording;
+mod rle;
+mod search;
+mod session;
+mod shapes;
 mod structures;
+mod svg;
+mod transform;
+mod ui;
+
+use std::panic::{self, AssertUnwindSafe};
 
 use game::Game;
 
+/// Parses the value following a command line flag.
+fn parse_value<T: std::str::FromStr>(flag: &str, value: Option<&String>) -> Result<T, String> {
+    let value = value.ok_or(format!("{} needs a value", flag))?;
+    value
+        .parse()
+        .map_err(|_| format!("bad value for {}: \"{}\"", flag, value))
+}
+
 pub fn run_game() -> Result<(), String> {
-    let mut game = Game::with_size(1280, 720);
-    game.game_loop()
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if args.first().map(|a| a == "--headless").unwrap_or(false) {
+        return headless::run_headless(headless::parse_args(&args[1..])?);
+    }
+
+    let mut game = Game::with_size(1280, 720, game::parse_args(&args)?);
+    game.refresh_title();
+    game.report_bank_issues();
+    game.offer_restore();
+
+    match panic::catch_unwind(AssertUnwindSafe(|| game.game_loop())) {
+        Ok(result) => result,
+        Err(cause) => {
+            // Keep whatever was on the board before going down.
+            game.autosave(true);
+            panic::resume_unwind(cause)
+        }
+    }
 }
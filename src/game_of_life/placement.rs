@@ -0,0 +1,159 @@
+use std::collections::{HashMap, HashSet};
+
+// Copies are evolved on the UI thread, so keep the total work small.
+const MAX_GEN_OFFSET: u32 = 1000;
+const MAX_COPIES: u32 = 1000;
+const MAX_TOTAL_GENERATIONS: u32 = 10_000;
+const MAX_SPACING: i32 = 100_000;
+
+/// A pattern and the corner it goes at.
+pub type PlacedPattern = ((i32, i32), Vec<Vec<u8>>);
+
+/// Places a structure as a grid of copies, `dx`/`dy` cells apart. Each copy
+/// after the first is run `gen_offset` generations further than the one
+/// before it, which staggers the phases of a stream of spaceships.
+#[derive(Clone, Copy)]
+pub struct ArraySettings {
+    pub cols: u32,
+    pub rows: u32,
+    pub dx: i32,
+    pub dy: i32,
+    pub gen_offset: u32,
+}
+
+impl ArraySettings {
+    /// Parses "cols rows dx dy [generation offset]", separated by spaces or
+    /// commas.
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let fields: Vec<&str> = text
+            .split(|c: char| c.is_whitespace() || c == ',')
+            .filter(|f| !f.is_empty())
+            .collect();
+        if !(4..=5).contains(&fields.len()) {
+            return Err("expected cols rows dx dy [generation offset]".to_string());
+        }
+
+        let number = |i: usize| -> Result<i32, String> {
+            fields[i].parse().map_err(|_| format!("\"{}\" is not a number", fields[i]))
+        };
+        let cols = number(0)?;
+        let rows = number(1)?;
+        if cols < 1 || rows < 1 {
+            return Err("need at least one row and column".to_string());
+        }
+        let gen_offset = if fields.len() == 5 { number(4)? } else { 0 };
+        if gen_offset < 0 {
+            return Err("the generation offset can't be negative".to_string());
+        }
+        if gen_offset as u32 > MAX_GEN_OFFSET {
+            return Err(format!("the generation offset can be at most {}", MAX_GEN_OFFSET));
+        }
+        let copies = (cols as u32).checked_mul(rows as u32).filter(|n| *n <= MAX_COPIES);
+        let Some(copies) = copies else {
+            return Err(format!("at most {} copies at once", MAX_COPIES));
+        };
+        let total = (copies - 1).checked_mul(gen_offset as u32).filter(|n| *n <= MAX_TOTAL_GENERATIONS);
+        if total.is_none() {
+            return Err(format!(
+                "copies times generation offset can be at most {} generations",
+                MAX_TOTAL_GENERATIONS
+            ));
+        }
+
+        let (dx, dy) = (number(2)?, number(3)?);
+        if dx.abs() > MAX_SPACING || dy.abs() > MAX_SPACING {
+            return Err(format!("spacing can be at most {} cells", MAX_SPACING));
+        }
+
+        Ok(ArraySettings {
+            cols: cols as u32,
+            rows: rows as u32,
+            dx,
+            dy,
+            gen_offset: gen_offset as u32,
+        })
+    }
+
+    pub fn describe(&self) -> String {
+        let mut text = format!("{}x{} every ({}, {})", self.cols, self.rows, self.dx, self.dy);
+        if self.gen_offset > 0 {
+            text.push_str(&format!(", +{} gen each", self.gen_offset));
+        }
+        text
+    }
+
+    /// Top-left corners of the copies, row by row.
+    pub fn origins(&self, origin: (i32, i32)) -> Vec<(i32, i32)> {
+        let mut origins = vec![];
+        for row in 0..self.rows as i32 {
+            for col in 0..self.cols as i32 {
+                origins.push((origin.0 + col * self.dx, origin.1 + row * self.dy));
+            }
+        }
+        origins
+    }
+
+    /// Every copy as a pattern and the corner to stamp it at. The pattern
+    /// is run forward once, each copy picking up where the one before it
+    /// left off.
+    pub fn copies(&self, origin: (i32, i32), cells: &[Vec<u8>]) -> Vec<PlacedPattern> {
+        let mut live: HashSet<(i32, i32)> = HashSet::new();
+        for (y, row) in cells.iter().enumerate() {
+            for (x, cell) in row.iter().enumerate() {
+                if *cell != 0 {
+                    live.insert((x as i32, y as i32));
+                }
+            }
+        }
+
+        self.origins(origin)
+            .into_iter()
+            .enumerate()
+            .map(|(i, (x, y))| {
+                // The first copy goes down as is, dead cells and all.
+                if i == 0 || self.gen_offset == 0 {
+                    return ((x, y), cells.to_vec());
+                }
+                for _ in 0..self.gen_offset {
+                    live = step_live(&live);
+                }
+                let ((ox, oy), grid) = live_to_grid(&live);
+                ((x + ox, y + oy), grid)
+            })
+            .collect()
+    }
+}
+
+/// One generation of B3/S23 on an unbounded plane.
+fn step_live(live: &HashSet<(i32, i32)>) -> HashSet<(i32, i32)> {
+    let mut neighbours: HashMap<(i32, i32), u8> = HashMap::new();
+    for (x, y) in live {
+        for dy in -1..=1 {
+            for dx in -1..=1 {
+                if (dx, dy) != (0, 0) {
+                    *neighbours.entry((x + dx, y + dy)).or_insert(0) += 1;
+                }
+            }
+        }
+    }
+    neighbours
+        .into_iter()
+        .filter(|(cell, count)| *count == 3 || (*count == 2 && live.contains(cell)))
+        .map(|(cell, _)| cell)
+        .collect()
+}
+
+/// Live cells as a `[y][x]` grid cropped to them, with the grid's corner.
+fn live_to_grid(live: &HashSet<(i32, i32)>) -> PlacedPattern {
+    let (Some(min_x), Some(min_y)) = (live.iter().map(|c| c.0).min(), live.iter().map(|c| c.1).min()) else {
+        return ((0, 0), vec![]);
+    };
+    let max_x = live.iter().map(|c| c.0).max().unwrap_or(min_x);
+    let max_y = live.iter().map(|c| c.1).max().unwrap_or(min_y);
+
+    let mut grid = vec![vec![0u8; (max_x - min_x + 1) as usize]; (max_y - min_y + 1) as usize];
+    for (x, y) in live {
+        grid[(y - min_y) as usize][(x - min_x) as usize] = 1;
+    }
+    ((min_x, min_y), grid)
+}
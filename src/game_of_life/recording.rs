@@ -0,0 +1,156 @@
+use std::{
+    fs::{self, File},
+    io::BufWriter,
+    path::Path,
+};
+
+use gif::{Encoder, Frame, Repeat};
+use sdl2::rect::Rect;
+
+use crate::game_of_life::board::Board;
+use crate::game_of_life::export::*;
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum RecordFormat {
+    Gif,
+    PngSequence,
+}
+
+#[derive(Clone, Copy)]
+pub struct RecordSettings {
+    pub format: RecordFormat,
+    pub region: Option<Rect>,
+    pub scale: u32,
+    /// Number of captures to drop between every recorded frame.
+    pub frame_skip: u32,
+    /// Playback delay per frame, in hundredths of a second.
+    pub delay: u16,
+}
+
+impl Default for RecordSettings {
+    fn default() -> Self {
+        RecordSettings {
+            format: RecordFormat::Gif,
+            region: None,
+            scale: 4,
+            frame_skip: 0,
+            delay: 5,
+        }
+    }
+}
+
+enum Target {
+    Gif(Encoder<BufWriter<File>>),
+    PngSequence(String),
+}
+
+/// Writes board snapshots to an animated GIF or a directory of numbered
+/// PNGs. It only sees the `Board`, so the window and headless runs share it.
+pub struct Recorder {
+    settings: RecordSettings,
+    region: Rect,
+    theme: Theme,
+    target: Target,
+    captures: u64,
+    frames: u64,
+}
+
+impl Recorder {
+    pub fn start(path: &str, settings: RecordSettings, theme: Theme, board: &Board) -> Result<Self, String> {
+        // The region is fixed up front, GIF frames all share one size.
+        let region = clip_region(board, settings.region)?;
+        let scale = settings.scale.max(1);
+        // Every frame is rasterized at this size, so refuse it up front.
+        let (width, height) = scaled_size(region, scale)?;
+
+        let target = match settings.format {
+            RecordFormat::Gif => {
+                if width > u16::MAX as u32 || height > u16::MAX as u32 {
+                    return Err(format!("{}x{} is too large for a GIF", width, height));
+                }
+                let file = File::create(path).map_err(|e| e.to_string())?;
+                let mut encoder = Encoder::new(
+                    BufWriter::new(file),
+                    width as u16,
+                    height as u16,
+                    &gif_palette(&theme),
+                )
+                .map_err(|e| e.to_string())?;
+                encoder.set_repeat(Repeat::Infinite).map_err(|e| e.to_string())?;
+                Target::Gif(encoder)
+            }
+            RecordFormat::PngSequence => {
+                fs::create_dir_all(path).map_err(|e| e.to_string())?;
+                Target::PngSequence(path.to_string())
+            }
+        };
+
+        Ok(Recorder {
+            settings: RecordSettings { scale, ..settings },
+            region,
+            theme,
+            target,
+            captures: 0,
+            frames: 0,
+        })
+    }
+
+    pub fn capture(&mut self, board: &Board) -> Result<(), String> {
+        let skip = !self.captures.is_multiple_of(self.settings.frame_skip as u64 + 1);
+        self.captures += 1;
+        if skip {
+            return Ok(());
+        }
+
+        let mut image = rasterize(board, Some(self.region), self.settings.scale, &self.theme)?;
+        match &mut self.target {
+            Target::Gif(encoder) => {
+                let indices = gif_indices(&image, &self.theme);
+                let mut frame = Frame::from_indexed_pixels(
+                    image.width as u16,
+                    image.height as u16,
+                    indices,
+                    None,
+                );
+                frame.delay = self.settings.delay;
+                encoder.write_frame(&frame).map_err(|e| e.to_string())?;
+            }
+            Target::PngSequence(dir) => {
+                let path = Path::new(dir).join(format!("frame_{:06}.png", self.frames));
+                image.save_png(&path.to_string_lossy())?;
+            }
+        }
+        self.frames += 1;
+
+        Ok(())
+    }
+
+    /// Number of frames written so far.
+    pub fn frames(&self) -> u64 {
+        self.frames
+    }
+}
+
+fn gif_palette(theme: &Theme) -> Vec<u8> {
+    vec![
+        theme.bg.r, theme.bg.g, theme.bg.b,
+        theme.dead.r, theme.dead.g, theme.dead.b,
+        theme.alive.r, theme.alive.g, theme.alive.b,
+    ]
+}
+
+fn gif_indices(image: &Image, theme: &Theme) -> Vec<u8> {
+    image
+        .pixels
+        .chunks_exact(4)
+        .map(|px| {
+            if px[..3] == [theme.alive.r, theme.alive.g, theme.alive.b] {
+                2
+            } else if px[..3] == [theme.dead.r, theme.dead.g, theme.dead.b] {
+                1
+            } else {
+                0
+            }
+        })
+        .collect()
+}
@@ -0,0 +1,400 @@
+//! Reading and writing the text pattern formats other Life programs use:
+//! RLE (Golly, LifeWiki), plaintext `.cells` and Golly's macrocell `.mc`.
+
+const RLE_LINE_LEN: usize = 70;
+// Macrocell files can describe patterns far bigger than any board; refuse
+// anything that wouldn't fit in a sensible grid.
+const MAX_PATTERN_SIZE: i64 = 4096;
+
+/// Encodes a `[y][x]` pattern as RLE for B3/S23.
+pub fn to_rle(cells: &[Vec<u8>]) -> String {
+    let rows = cells.len();
+    let cols = cells.iter().map(|r| r.len()).max().unwrap_or(0);
+    let mut body = String::new();
+    let mut pending_rows = 0;
+
+    for row in cells {
+        // Trailing dead cells in a row are implied.
+        let end = row.iter().rposition(|c| *c != 0).map(|i| i + 1).unwrap_or(0);
+        if end == 0 {
+            pending_rows += 1;
+            continue;
+        }
+        if body.is_empty() {
+            push_run(&mut body, pending_rows, '$');
+        } else {
+            push_run(&mut body, pending_rows + 1, '$');
+        }
+        pending_rows = 0;
+
+        let mut x = 0;
+        while x < end {
+            let alive = row[x] != 0;
+            let run = row[x..end].iter().take_while(|c| (**c != 0) == alive).count();
+            push_run(&mut body, run, if alive { 'o' } else { 'b' });
+            x += run;
+        }
+    }
+    body.push('!');
+
+    let mut rle = format!("x = {}, y = {}, rule = B3/S23\n", cols, rows);
+    let mut line_len = 0;
+    for token in split_tokens(&body) {
+        if line_len + token.len() > RLE_LINE_LEN {
+            rle.push('\n');
+            line_len = 0;
+        }
+        rle.push_str(token);
+        line_len += token.len();
+    }
+    rle.push('\n');
+    rle
+}
+
+fn push_run(out: &mut String, run: usize, tag: char) {
+    if run == 0 {
+        return;
+    }
+    if run > 1 {
+        out.push_str(&run.to_string());
+    }
+    out.push(tag);
+}
+
+/// Splits an RLE body into count+tag tokens so lines are only broken
+/// between them.
+fn split_tokens(body: &str) -> Vec<&str> {
+    let mut tokens = vec![];
+    let mut start = 0;
+    for (i, c) in body.char_indices() {
+        if !c.is_ascii_digit() {
+            tokens.push(&body[start..i + c.len_utf8()]);
+            start = i + c.len_utf8();
+        }
+    }
+    tokens
+}
+
+/// Parses an RLE pattern. `#` comment lines are skipped, the header's size
+/// is kept as a minimum, and any state other than `b`/`.` counts as alive.
+pub fn parse_rle(text: &str) -> Result<Vec<Vec<u8>>, String> {
+    let mut rows: Vec<Vec<u8>> = vec![vec![]];
+    let mut count = String::new();
+    let mut saw_header = false;
+    let mut size = (0, 0);
+    let mut finished = false;
+    let limit = MAX_PATTERN_SIZE as usize;
+
+    for line in text.lines().map(str::trim) {
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        if line.starts_with('x') && line.contains('=') {
+            saw_header = true;
+            size = header_size(line);
+            if size.0 > limit || size.1 > limit {
+                return Err("pattern is too large to load".to_string());
+            }
+            continue;
+        }
+
+        for c in line.chars() {
+            if c.is_ascii_digit() {
+                count.push(c);
+                continue;
+            }
+            let run = match count.parse::<usize>() {
+                Ok(n) => n,
+                Err(_) if count.is_empty() => 1,
+                Err(_) => return Err("pattern is too large to load".to_string()),
+            };
+            match c {
+                'b' | '.' | 'o' | 'A'..='X' => {
+                    let row = rows.last_mut().unwrap();
+                    if row.len() + run > limit {
+                        return Err("pattern is too large to load".to_string());
+                    }
+                    let state = if matches!(c, 'b' | '.') { 0 } else { 1 };
+                    row.extend(std::iter::repeat_n(state, run));
+                }
+                '$' => {
+                    if rows.len() + run > limit {
+                        return Err("pattern is too large to load".to_string());
+                    }
+                    for _ in 0..run {
+                        rows.push(vec![]);
+                    }
+                }
+                '!' => {
+                    finished = true;
+                    break;
+                }
+                c if c.is_whitespace() => {}
+                c => return Err(format!("unexpected '{}' in RLE", c)),
+            }
+            count.clear();
+        }
+        if finished {
+            break;
+        }
+    }
+
+    if !saw_header && !finished {
+        return Err("not an RLE pattern".to_string());
+    }
+    let mut rows = pad_rows(rows);
+    if rows.len() < size.1 {
+        rows.resize(size.1, vec![]);
+    }
+    let cols = rows.iter().map(|r| r.len()).max().unwrap_or(0).max(size.0);
+    for row in &mut rows {
+        row.resize(cols, 0);
+    }
+    Ok(rows)
+}
+
+/// The width and height from an `x = 3, y = 3, rule = ...` header line.
+fn header_size(line: &str) -> (usize, usize) {
+    let mut size = (0, 0);
+    for field in line.split(',') {
+        let Some((key, value)) = field.split_once('=') else {
+            continue;
+        };
+        let value = value.trim().parse().unwrap_or(0);
+        match key.trim() {
+            "x" => size.0 = value,
+            "y" => size.1 = value,
+            _ => {}
+        }
+    }
+    size
+}
+
+/// Parses a plaintext pattern: `O` (or `*`) alive, `.` dead, `!` comments.
+pub fn parse_plaintext(text: &str) -> Result<Vec<Vec<u8>>, String> {
+    let mut rows = vec![];
+
+    for line in text.lines() {
+        let line = line.trim_end();
+        if line.starts_with('!') {
+            continue;
+        }
+        let row = line
+            .chars()
+            .map(|c| match c {
+                'O' | 'o' | '*' => Ok(1),
+                '.' => Ok(0),
+                c => Err(format!("unexpected '{}' in plaintext pattern", c)),
+            })
+            .collect::<Result<Vec<u8>, String>>()?;
+        rows.push(row);
+    }
+
+    // Blank lines at either end are just layout, blank lines inside are
+    // dead rows.
+    while rows.last().is_some_and(|r| r.is_empty()) {
+        rows.pop();
+    }
+    let first = rows.iter().position(|r| !r.is_empty()).unwrap_or(rows.len());
+    rows.drain(..first);
+
+    if rows.is_empty() {
+        return Err("empty pattern".to_string());
+    }
+    Ok(pad_rows(rows))
+}
+
+/// Parses text as RLE if it looks like RLE, otherwise as plaintext.
+pub fn parse_pattern(text: &str) -> Result<Vec<Vec<u8>>, String> {
+    let looks_like_rle = text
+        .lines()
+        .map(str::trim)
+        .find(|l| !l.is_empty() && !l.starts_with('#') && !l.starts_with('!'))
+        .is_some_and(|l| (l.starts_with('x') && l.contains('=')) || l.contains('$') || l.ends_with('!'));
+
+    if looks_like_rle {
+        parse_rle(text)
+    } else {
+        parse_plaintext(text)
+    }
+}
+
+enum MacroNode {
+    Leaf(Vec<(i64, i64)>),
+    Inner(u32, [usize; 4]),
+}
+
+/// Parses a two-state macrocell pattern into a grid cropped to its live
+/// cells.
+pub fn parse_macrocell(text: &str) -> Result<Vec<Vec<u8>>, String> {
+    // Node 0 is the empty node; the rest are numbered from 1 in file order.
+    let mut nodes = vec![MacroNode::Leaf(vec![])];
+
+    for line in text.lines().map(str::trim) {
+        if line.is_empty() || line.starts_with('[') || line.starts_with('#') {
+            continue;
+        }
+        if line.starts_with(['.', '*', '$']) {
+            let mut live = vec![];
+            let (mut x, mut y) = (0, 0);
+            for c in line.chars() {
+                match c {
+                    '.' => x += 1,
+                    '*' => {
+                        live.push((x, y));
+                        x += 1;
+                    }
+                    '$' => {
+                        x = 0;
+                        y += 1;
+                    }
+                    c => return Err(format!("unexpected '{}' in macrocell leaf", c)),
+                }
+            }
+            nodes.push(MacroNode::Leaf(live));
+            continue;
+        }
+
+        let fields: Vec<usize> = line
+            .split_whitespace()
+            .map(|f| f.parse().map_err(|_| format!("bad macrocell line \"{}\"", line)))
+            .collect::<Result<_, String>>()?;
+        let [level, nw, ne, sw, se] = fields[..] else {
+            return Err(format!("bad macrocell line \"{}\"", line));
+        };
+        if !(4..=62).contains(&level) || [nw, ne, sw, se].iter().any(|c| *c >= nodes.len()) {
+            return Err(format!("bad macrocell node \"{}\"", line));
+        }
+        nodes.push(MacroNode::Inner(level as u32, [nw, ne, sw, se]));
+    }
+    if nodes.len() < 2 {
+        return Err("empty macrocell pattern".to_string());
+    }
+
+    let mut live = vec![];
+    collect_macro_cells(&nodes, nodes.len() - 1, (0, 0), &mut live)?;
+    let min_x = live.iter().map(|c| c.0).min().ok_or("empty macrocell pattern")?;
+    let min_y = live.iter().map(|c| c.1).min().unwrap_or(0);
+    let max_x = live.iter().map(|c| c.0).max().unwrap_or(0);
+    let max_y = live.iter().map(|c| c.1).max().unwrap_or(0);
+    if max_x - min_x >= MAX_PATTERN_SIZE || max_y - min_y >= MAX_PATTERN_SIZE {
+        return Err("pattern is too large to load".to_string());
+    }
+
+    let mut rows = vec![vec![0u8; (max_x - min_x + 1) as usize]; (max_y - min_y + 1) as usize];
+    for (x, y) in live {
+        rows[(y - min_y) as usize][(x - min_x) as usize] = 1;
+    }
+    Ok(rows)
+}
+
+fn collect_macro_cells(
+    nodes: &[MacroNode],
+    idx: usize,
+    (x0, y0): (i64, i64),
+    live: &mut Vec<(i64, i64)>,
+) -> Result<(), String> {
+    match &nodes[idx] {
+        MacroNode::Leaf(cells) => {
+            live.extend(cells.iter().map(|(x, y)| (x0 + x, y0 + y)));
+        }
+        MacroNode::Inner(level, children) => {
+            let half = 1i64 << (level - 1);
+            let corners = [(0, 0), (half, 0), (0, half), (half, half)];
+            for (child, (dx, dy)) in children.iter().zip(corners) {
+                if *child != 0 {
+                    collect_macro_cells(nodes, *child, (x0 + dx, y0 + dy), live)?;
+                }
+            }
+        }
+    }
+    if live.len() as i64 > MAX_PATTERN_SIZE * MAX_PATTERN_SIZE {
+        return Err("pattern is too large to load".to_string());
+    }
+    Ok(())
+}
+
+fn pad_rows(mut rows: Vec<Vec<u8>>) -> Vec<Vec<u8>> {
+    while rows.last().is_some_and(|r| r.is_empty()) && rows.len() > 1 {
+        rows.pop();
+    }
+    let cols = rows.iter().map(|r| r.len()).max().unwrap_or(0);
+    for row in &mut rows {
+        row.resize(cols, 0);
+    }
+    rows
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn grid(rows: &[&str]) -> Vec<Vec<u8>> {
+        rows.iter()
+            .map(|r| r.chars().map(|c| (c == 'O') as u8).collect())
+            .collect()
+    }
+
+    #[test]
+    fn glider_round_trips() {
+        let glider = grid(&[".O.", "..O", "OOO"]);
+        let rle = to_rle(&glider);
+        assert_eq!(rle, "x = 3, y = 3, rule = B3/S23\nbo$2bo$3o!\n");
+        assert_eq!(parse_rle(&rle).unwrap(), glider);
+    }
+
+    #[test]
+    fn blank_rows_and_columns_round_trip() {
+        let cells = grid(&["....", "....", ".O..", "....", "O...", "...."]);
+        assert_eq!(parse_rle(&to_rle(&cells)).unwrap(), cells);
+    }
+
+    #[test]
+    fn long_patterns_wrap_and_round_trip() {
+        let row: String = (0..200).map(|i| if i % 3 == 0 { 'O' } else { '.' }).collect();
+        let cells = grid(&[&row, &row]);
+        let rle = to_rle(&cells);
+        assert!(rle.lines().all(|l| l.len() <= RLE_LINE_LEN));
+        assert_eq!(parse_rle(&rle).unwrap(), cells);
+    }
+
+    #[test]
+    fn parses_comments_and_multistate_cells() {
+        let rle = "#N blinker\n#C a comment\nx = 3, y = 1, rule = B3/S23\n3A!";
+        assert_eq!(parse_rle(rle).unwrap(), vec![vec![1, 1, 1]]);
+    }
+
+    #[test]
+    fn rejects_oversized_header() {
+        assert!(parse_rle("x = 100000, y = 3\no!").is_err());
+        assert!(parse_rle("x = 3, y = 100000\no!").is_err());
+    }
+
+    #[test]
+    fn rejects_oversized_runs() {
+        assert!(parse_rle("x = 1, y = 1\n999999999o!").is_err());
+        assert!(parse_rle("x = 1, y = 1\n99999999999999999999999o!").is_err());
+        assert!(parse_rle("x = 1, y = 1\no999999999$o!").is_err());
+        assert!(parse_rle("x = 1, y = 1\n4000o4000bo!").is_err());
+    }
+
+    #[test]
+    fn rejects_malformed_rle() {
+        assert!(parse_rle("x = 3, y = 1\nozo!").is_err());
+        assert!(parse_rle("bo$2bo").is_err());
+    }
+
+    #[test]
+    fn parses_plaintext() {
+        let text = "!Name: glider\n\n.O\n..O\nOOO\n\n";
+        assert_eq!(parse_plaintext(text).unwrap(), grid(&[".O.", "..O", "OOO"]));
+        assert!(parse_plaintext("!only a comment\n").is_err());
+        assert!(parse_plaintext(".O\nx.\n").is_err());
+    }
+
+    #[test]
+    fn detects_the_format() {
+        assert_eq!(parse_pattern("x = 2, y = 1\n2o!").unwrap(), vec![vec![1, 1]]);
+        assert_eq!(parse_pattern("OO\n").unwrap(), vec![vec![1, 1]]);
+    }
+}
@@ -0,0 +1,62 @@
+use crate::game_of_life::structures::Structures;
+
+/// Scores `text` against `query` as a case-insensitive subsequence match.
+/// Runs of consecutive characters and matches at the start of a word score
+/// higher; `None` means not every query character was found in order.
+pub fn fuzzy_score(query: &str, text: &str) -> Option<i32> {
+    let query: Vec<char> = query.to_lowercase().chars().filter(|c| !c.is_whitespace()).collect();
+    if query.is_empty() {
+        return Some(0);
+    }
+
+    let text: Vec<char> = text.to_lowercase().chars().collect();
+    let mut score = 0;
+    let mut qi = 0;
+    let mut last_match: Option<usize> = None;
+
+    for (ti, c) in text.iter().enumerate() {
+        if qi == query.len() {
+            break;
+        }
+        if *c != query[qi] {
+            continue;
+        }
+
+        score += 1;
+        if last_match.is_some_and(|last| last + 1 == ti) {
+            score += 5;
+        }
+        if ti == 0 || !text[ti - 1].is_alphanumeric() {
+            score += 3;
+        }
+        last_match = Some(ti);
+        qi += 1;
+    }
+
+    if qi < query.len() {
+        return None;
+    }
+    // Prefer tighter, shorter matches among otherwise equal ones.
+    Some(score * 4 - text.len() as i32 / 8)
+}
+
+/// Every entry in every bank matching `query`, best first, as
+/// (bank index, entry index) pairs. Nickname matches outrank matches that
+/// only hit the metadata.
+pub fn search_banks(banks: &[Structures], query: &str) -> Vec<(usize, usize)> {
+    let mut results: Vec<(i32, usize, usize)> = vec![];
+
+    for (bank_idx, bank) in banks.iter().enumerate() {
+        for idx in 0..bank.len() {
+            let name = bank.nickname(idx).unwrap_or_default();
+            let by_name = fuzzy_score(query, name).map(|s| s * 2);
+            let by_metadata = fuzzy_score(query, &bank.search_text(idx));
+            if let Some(score) = by_name.max(by_metadata) {
+                results.push((score, bank_idx, idx));
+            }
+        }
+    }
+
+    results.sort_by(|a, b| b.0.cmp(&a.0).then(a.1.cmp(&b.1)).then(a.2.cmp(&b.2)));
+    results.into_iter().map(|(_, bank_idx, idx)| (bank_idx, idx)).collect()
+}
@@ -0,0 +1,84 @@
+use std::{fs, io, path::Path};
+
+use serde::{Deserialize, Serialize};
+
+use crate::game_of_life::board::Board;
+use crate::game_of_life::game::Game;
+
+/// Largest board a session can ask for, in cells along either side.
+pub const MAX_BOARD_SIZE: i32 = 10_000;
+
+#[derive(Serialize, Deserialize)]
+pub struct Session {
+    pub width: i32,
+    pub height: i32,
+    pub live_cells: Vec<(i32, i32)>,
+    pub generation: u64,
+
+    pub cam_offset_x: i32,
+    pub cam_offset_y: i32,
+    pub cell_width: i32,
+    pub cell_height: i32,
+    pub dark_mode: bool,
+    pub denom: i32,
+    pub strctr_idx: usize,
+    #[serde(default)]
+    pub bank: Option<String>,
+    #[serde(default = "default_wrap")]
+    pub wrap: bool,
+}
+
+fn default_wrap() -> bool {
+    true
+}
+
+impl Session {
+    /// Checks the sizes before anything is allocated or divided by them.
+    pub fn validate(&self) -> Result<(), String> {
+        let board_sizes = 1..=MAX_BOARD_SIZE;
+        if !board_sizes.contains(&self.width) || !board_sizes.contains(&self.height) {
+            return Err(format!(
+                "board size {}x{} is outside 1 to {}",
+                self.width, self.height, MAX_BOARD_SIZE
+            ));
+        }
+        let cell_sizes = Game::MIN_CELL_SIZE..=Game::MAX_CELL_SIZE;
+        if !cell_sizes.contains(&self.cell_width) || !cell_sizes.contains(&self.cell_height) {
+            return Err(format!(
+                "cell size {}x{} is outside {} to {}",
+                self.cell_width, self.cell_height, Game::MIN_CELL_SIZE, Game::MAX_CELL_SIZE
+            ));
+        }
+        Ok(())
+    }
+
+    pub fn to_board(&self) -> Board {
+        let mut board = Board::with_size(self.width, self.height);
+        board.set_live_cells(&self.live_cells);
+        board.wrap = self.wrap;
+        board
+    }
+}
+
+pub fn save_session(path: &str, session: &Session) -> io::Result<()> {
+    let contents = serde_json::to_string(session)?;
+
+    // Write to a sibling file first so a crash mid-write never leaves a
+    // truncated session behind.
+    let tmp_path = format!("{}.tmp", path);
+    fs::write(&tmp_path, contents)?;
+    fs::rename(&tmp_path, path)
+}
+
+pub fn load_session(path: &str) -> io::Result<Session> {
+    let contents = fs::read_to_string(path)?;
+    let session: Session = serde_json::from_str(&contents)?;
+    session
+        .validate()
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+    Ok(session)
+}
+
+pub fn session_exists(path: &str) -> bool {
+    Path::new(path).is_file()
+}
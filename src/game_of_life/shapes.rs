@@ -0,0 +1,248 @@
+//! Cells covered by the drawing tools, in board coordinates. Points can fall
+//! off the board; `Board::wrap_coords` decides what happens to them.
+
+use std::collections::{HashSet, VecDeque};
+
+use crate::game_of_life::board::Board;
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum Tool {
+    Freehand,
+    Line,
+    Rect,
+    FilledRect,
+    Ellipse,
+    FilledEllipse,
+    /// Fills the dead area under the cursor, or clears the live object.
+    Fill,
+    /// Selects the live object under the cursor.
+    Wand,
+}
+
+impl Tool {
+    pub fn label(&self) -> &'static str {
+        match self {
+            Tool::Freehand => "freehand",
+            Tool::Line => "line",
+            Tool::Rect => "rectangle",
+            Tool::FilledRect => "filled rectangle",
+            Tool::Ellipse => "ellipse",
+            Tool::FilledEllipse => "filled ellipse",
+            Tool::Fill => "fill",
+            Tool::Wand => "magic wand",
+        }
+    }
+
+    /// The cells the shape dragged from `a` to `b` covers.
+    pub fn points(&self, a: (i32, i32), b: (i32, i32)) -> Vec<(i32, i32)> {
+        match self {
+            Tool::Freehand | Tool::Line => line_points(a, b),
+            Tool::Rect => rect_points(a, b, false),
+            Tool::FilledRect => rect_points(a, b, true),
+            Tool::Ellipse => ellipse_points(a, b, false),
+            Tool::FilledEllipse => ellipse_points(a, b, true),
+            Tool::Fill | Tool::Wand => vec![],
+        }
+    }
+}
+
+pub const MAX_BRUSH_RADIUS: i32 = 20;
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum BrushShape {
+    Square,
+    Round,
+}
+
+/// The cells a freehand stroke paints around each point. A radius of 1 is
+/// a single cell. A spray brush only hits each cell with `spray_chance`.
+#[derive(Clone, Copy)]
+pub struct Brush {
+    pub shape: BrushShape,
+    pub radius: i32,
+    pub spray: bool,
+    pub spray_chance: f64,
+}
+
+impl Default for Brush {
+    fn default() -> Self {
+        Brush {
+            shape: BrushShape::Square,
+            radius: 1,
+            spray: false,
+            spray_chance: 0.2,
+        }
+    }
+}
+
+impl Brush {
+    pub fn footprint(&self, (cx, cy): (i32, i32)) -> Vec<(i32, i32)> {
+        let r = self.radius.clamp(1, MAX_BRUSH_RADIUS) - 1;
+        let limit = (r as f64 + 0.5).powi(2);
+        let mut points = vec![];
+        for dy in -r..=r {
+            for dx in -r..=r {
+                if self.shape == BrushShape::Round && (dx * dx + dy * dy) as f64 > limit {
+                    continue;
+                }
+                points.push((cx + dx, cy + dy));
+            }
+        }
+        points
+    }
+
+    pub fn describe(&self) -> String {
+        let shape = match self.shape {
+            BrushShape::Square => "square",
+            BrushShape::Round => "round",
+        };
+        let mut text = format!("{} brush {}", shape, self.radius);
+        if self.spray {
+            text.push_str(&format!(", spray {:.0}%", self.spray_chance * 100.0));
+        }
+        text
+    }
+}
+
+fn line_low((x0, y0): (i32, i32), (x1, y1): (i32, i32), points: &mut Vec<(i32, i32)>) {
+    let dx = x1 - x0;
+    let mut dy = y1 - y0;
+    let mut yi = 1i32;
+    if dy < 0 {
+        yi = -1;
+        dy = -dy;
+    }
+    let mut d = (dy * 2) - dx;
+    let mut y = y0;
+    for x in x0..=x1 {
+        points.push((x, y));
+        if d > 0 {
+            y += yi;
+            d += 2 * (dy - dx);
+        } else {
+            d += dy * 2;
+        }
+    }
+}
+
+fn line_high((x0, y0): (i32, i32), (x1, y1): (i32, i32), points: &mut Vec<(i32, i32)>) {
+    let mut dx = x1 - x0;
+    let dy = y1 - y0;
+    let mut xi = 1i32;
+    if dx < 0 {
+        xi = -1;
+        dx = -dx;
+    }
+    let mut d = (dx * 2) - dy;
+    let mut x = x0;
+    for y in y0..=y1 {
+        points.push((x, y));
+        if d > 0 {
+            x += xi;
+            d += 2 * (dx - dy);
+        } else {
+            d += dx * 2;
+        }
+    }
+}
+
+/// Bresenham line between two cells, both ends included.
+pub fn line_points(a: (i32, i32), b: (i32, i32)) -> Vec<(i32, i32)> {
+    let mut points = vec![];
+    if (b.1 - a.1).abs() < (b.0 - a.0).abs() {
+        if a.0 > b.0 {
+            line_low(b, a, &mut points);
+        } else {
+            line_low(a, b, &mut points);
+        }
+    } else if a.1 > b.1 {
+        line_high(b, a, &mut points);
+    } else {
+        line_high(a, b, &mut points);
+    }
+    points
+}
+
+pub fn rect_points(a: (i32, i32), b: (i32, i32), filled: bool) -> Vec<(i32, i32)> {
+    let (x0, x1) = (a.0.min(b.0), a.0.max(b.0));
+    let (y0, y1) = (a.1.min(b.1), a.1.max(b.1));
+    let mut points = vec![];
+    for y in y0..=y1 {
+        for x in x0..=x1 {
+            if filled || x == x0 || x == x1 || y == y0 || y == y1 {
+                points.push((x, y));
+            }
+        }
+    }
+    points
+}
+
+/// The ellipse inscribed in the box between two corners. The outline is
+/// the filled ellipse's cells that touch a cell outside it, which keeps it
+/// connected at any size.
+pub fn ellipse_points(a: (i32, i32), b: (i32, i32), filled: bool) -> Vec<(i32, i32)> {
+    let (x0, x1) = (a.0.min(b.0), a.0.max(b.0));
+    let (y0, y1) = (a.1.min(b.1), a.1.max(b.1));
+    let (cx, cy) = ((x0 + x1) as f64 / 2.0, (y0 + y1) as f64 / 2.0);
+    let (rx, ry) = ((x1 - x0) as f64 / 2.0 + 0.5, (y1 - y0) as f64 / 2.0 + 0.5);
+    let inside = |x: i32, y: i32| {
+        let (dx, dy) = ((x as f64 - cx) / rx, (y as f64 - cy) / ry);
+        dx * dx + dy * dy <= 1.0
+    };
+
+    let mut points = vec![];
+    for y in y0..=y1 {
+        for x in x0..=x1 {
+            if !inside(x, y) {
+                continue;
+            }
+            let edge = !inside(x - 1, y) || !inside(x + 1, y) || !inside(x, y - 1) || !inside(x, y + 1);
+            if filled || edge {
+                points.push((x, y));
+            }
+        }
+    }
+    points
+}
+
+/// Every cell connected to `start` that has the same state. With `reach` 0
+/// cells connect through their sides only; otherwise through any cell up to
+/// `reach` away in each direction, so a reach of 2 joins cells with a
+/// one-cell gap between them. The points are not wrapped, so an object
+/// crossing a wrapping edge stays in one piece.
+pub fn connected_cells(board: &Board, start: (i32, i32), reach: i32) -> Vec<(i32, i32)> {
+    let Some((sx, sy)) = board.wrap_coords(start.0, start.1) else {
+        return vec![];
+    };
+    let state = board.cells[sx as usize][sy as usize];
+
+    let mut offsets = vec![];
+    if reach == 0 {
+        offsets.extend([(1, 0), (-1, 0), (0, 1), (0, -1)]);
+    } else {
+        for dy in -reach..=reach {
+            for dx in -reach..=reach {
+                if (dx, dy) != (0, 0) {
+                    offsets.push((dx, dy));
+                }
+            }
+        }
+    }
+
+    let mut seen = HashSet::from([(sx, sy)]);
+    let mut queue = VecDeque::from([start]);
+    let mut points = vec![];
+    while let Some((x, y)) = queue.pop_front() {
+        points.push((x, y));
+        for (dx, dy) in &offsets {
+            let next = (x + dx, y + dy);
+            let Some((wx, wy)) = board.wrap_coords(next.0, next.1) else {
+                continue;
+            };
+            if board.cells[wx as usize][wy as usize] == state && seen.insert((wx, wy)) {
+                queue.push_back(next);
+            }
+        }
+    }
+    points
+}
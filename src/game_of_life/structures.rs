@@ -1,37 +1,675 @@
-use std::{fs, io};
+use std::{
+    collections::HashSet,
+    fmt, fs, io,
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
 
 use serde::Deserialize;
+use serde_json::Value;
 
-#[derive(Deserialize)]
+#[derive(Deserialize, Clone, Copy, PartialEq, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum Category {
+    Spaceship,
+    Gun,
+    Methuselah,
+    Oscillator,
+    StillLife,
+    Puffer,
+    #[serde(other)]
+    Other,
+}
+
+impl Category {
+    pub const ALL: [Category; 7] = [
+        Category::Spaceship,
+        Category::Gun,
+        Category::Methuselah,
+        Category::Oscillator,
+        Category::StillLife,
+        Category::Puffer,
+        Category::Other,
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Category::Spaceship => "spaceship",
+            Category::Gun => "gun",
+            Category::Methuselah => "methuselah",
+            Category::Oscillator => "oscillator",
+            Category::StillLife => "still life",
+            Category::Puffer => "puffer",
+            Category::Other => "other",
+        }
+    }
+
+    /// The name used for this category in bank files.
+    pub fn key(&self) -> &'static str {
+        match self {
+            Category::StillLife => "still_life",
+            _ => self.label(),
+        }
+    }
+}
+
+/// Optional catalogue details. Every field may be left out of the JSON.
+#[derive(Deserialize, Default, Clone)]
+#[serde(default)]
+pub struct Metadata {
+    pub rule: Option<String>,
+    pub period: Option<u32>,
+    /// Cells moved (dx, dy) over one period.
+    pub displacement: Option<(i32, i32)>,
+    pub category: Option<Category>,
+    pub discoverer: Option<String>,
+    pub year: Option<u32>,
+    pub description: Option<String>,
+    pub url: Option<String>,
+}
+
+impl Metadata {
+    /// Speed in the usual notation, e.g. "c/4 diagonal" or "2c/5 orthogonal".
+    pub fn speed(&self) -> Option<String> {
+        let (dx, dy) = self.displacement?;
+        let period = self.period?;
+        let (dx, dy) = (dx.unsigned_abs(), dy.unsigned_abs());
+        if dx == 0 && dy == 0 {
+            return None;
+        }
+
+        let mut steps = dx.max(dy);
+        let mut period = period;
+        let (mut a, mut b) = (steps, period);
+        while b != 0 {
+            (a, b) = (b, a % b);
+        }
+        if a > 1 {
+            steps /= a;
+            period /= a;
+        }
+
+        let direction = if dx == 0 || dy == 0 {
+            "orthogonal"
+        } else if dx == dy {
+            "diagonal"
+        } else {
+            "oblique"
+        };
+        let numerator = if steps == 1 { String::new() } else { steps.to_string() };
+        Some(format!("{}c/{} {}", numerator, period, direction))
+    }
+
+    /// The fields that are set, as JSON key/value pairs in bank file order.
+    fn json_fields(&self) -> Vec<(&'static str, String)> {
+        let mut fields = vec![];
+        if let Some(category) = self.category {
+            fields.push(("category", format!("\"{}\"", category.key())));
+        }
+        if let Some(rule) = &self.rule {
+            fields.push(("rule", quote(rule)));
+        }
+        if let Some(period) = self.period {
+            fields.push(("period", period.to_string()));
+        }
+        if let Some((dx, dy)) = self.displacement {
+            fields.push(("displacement", format!("[{}, {}]", dx, dy)));
+        }
+        if let Some(discoverer) = &self.discoverer {
+            fields.push(("discoverer", quote(discoverer)));
+        }
+        if let Some(year) = self.year {
+            fields.push(("year", year.to_string()));
+        }
+        if let Some(description) = &self.description {
+            fields.push(("description", quote(description)));
+        }
+        if let Some(url) = &self.url {
+            fields.push(("url", quote(url)));
+        }
+        fields
+    }
+
+    /// One-line summary of whichever fields are set.
+    pub fn summary(&self) -> String {
+        let mut parts = vec![];
+        if let Some(category) = self.category {
+            parts.push(category.label().to_string());
+        }
+        if let Some(period) = self.period {
+            parts.push(format!("p{}", period));
+        }
+        if let Some(speed) = self.speed() {
+            parts.push(speed);
+        }
+        if let Some(rule) = &self.rule {
+            parts.push(rule.clone());
+        }
+        match (&self.discoverer, self.year) {
+            (Some(who), Some(year)) => parts.push(format!("{} {}", who, year)),
+            (Some(who), None) => parts.push(who.clone()),
+            (None, Some(year)) => parts.push(year.to_string()),
+            _ => {}
+        }
+        parts.join(", ")
+    }
+}
+
+#[derive(Deserialize, Clone)]
 struct Structure {
     nickname: String,
-    cells: Vec<Vec<u8>>
+    cells: Vec<Vec<u8>>,
+    #[serde(flatten)]
+    metadata: Metadata,
+}
+
+/// Narrows which bank entries A/D cycle through. Unset fields match
+/// anything.
+#[derive(Default, Clone)]
+pub struct StructureFilter {
+    pub category: Option<Category>,
+    pub rule: Option<String>,
+}
+
+impl StructureFilter {
+    fn matches(&self, metadata: &Metadata) -> bool {
+        if self.category.is_some() && metadata.category != self.category {
+            return false;
+        }
+        if let Some(rule) = &self.rule {
+            if !metadata.rule.as_ref().is_some_and(|r| r.eq_ignore_ascii_case(rule)) {
+                return false;
+            }
+        }
+        true
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.category.is_some() || self.rule.is_some()
+    }
+
+    pub fn describe(&self) -> String {
+        let mut parts = vec![];
+        if let Some(category) = self.category {
+            parts.push(category.label().to_string());
+        }
+        if let Some(rule) = &self.rule {
+            parts.push(rule.clone());
+        }
+        parts.join(", ")
+    }
 }
 
-#[derive(Deserialize)]
 pub struct Structures {
     name: String,
     structures: Vec<Structure>,
+    issues: Vec<BankIssue>,
+    path: Option<String>,
+    modified: Option<SystemTime>,
+}
+
+/// A problem found while loading a bank, pointing at where in the JSON it
+/// was found.
+pub struct BankIssue {
+    pub location: String,
+    pub structure: Option<String>,
+    pub message: String,
+}
+
+impl fmt::Display for BankIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.structure {
+            Some(name) => write!(f, "{} (\"{}\"): {}", self.location, name, self.message),
+            None => write!(f, "{}: {}", self.location, self.message),
+        }
+    }
+}
+
+fn validate_cells(cells: &Value) -> Result<(), String> {
+    let rows = cells.as_array().ok_or("\"cells\" is not an array")?;
+    if rows.is_empty() {
+        return Err("pattern is empty".to_string());
+    }
+
+    let mut width = None;
+    let mut any_alive = false;
+    for (y, row) in rows.iter().enumerate() {
+        let row = row
+            .as_array()
+            .ok_or(format!("cells[{}] is not an array", y))?;
+        match width {
+            None => width = Some(row.len()),
+            Some(w) if w != row.len() => {
+                return Err(format!(
+                    "cells[{}] has {} cells, expected {} like the rows above",
+                    y,
+                    row.len(),
+                    w
+                ));
+            }
+            _ => {}
+        }
+
+        for (x, value) in row.iter().enumerate() {
+            match value.as_u64() {
+                Some(0) => {}
+                Some(1) => any_alive = true,
+                _ => {
+                    return Err(format!(
+                        "cells[{}][{}] is {}, expected 0 or 1",
+                        y, x, value
+                    ));
+                }
+            }
+        }
+    }
+
+    if width == Some(0) || !any_alive {
+        return Err("pattern is empty".to_string());
+    }
+
+    Ok(())
 }
 
-pub fn load_from_json(path: String) -> io::Result<Structures> {
-    let contents = fs::read_to_string(path).unwrap_or("".to_string());
-    let structures: Structures = serde_json::from_str(&contents)?;
-    println!("# structures on bank \"{}\": {}", &structures.name, &structures.structures.len());
-    for s in &structures.structures {
+/// Loads a structure bank. Entries that fail validation are left out and
+/// recorded as issues, so one bad pattern doesn't take the whole bank down.
+/// Only an unreadable file or malformed JSON is an error.
+pub fn load_from_json(path: &str) -> Result<Structures, BankIssue> {
+    let fatal = |location: String, message: String| BankIssue {
+        location,
+        structure: None,
+        message,
+    };
+
+    let contents = fs::read_to_string(path).map_err(|e| fatal(path.to_string(), e.to_string()))?;
+    let root: Value = serde_json::from_str(&contents).map_err(|e| {
+        fatal(format!("{}:{}:{}", path, e.line(), e.column()), e.to_string())
+    })?;
+
+    let mut issues = vec![];
+    let name = match root.get("name").and_then(Value::as_str) {
+        Some(name) => name.to_string(),
+        None => {
+            issues.push(fatal(format!("{}: name", path), "missing bank name".to_string()));
+            path.to_string()
+        }
+    };
+    let entries = root
+        .get("structures")
+        .and_then(Value::as_array)
+        .ok_or_else(|| fatal(format!("{}: structures", path), "missing \"structures\" array".to_string()))?;
+
+    let mut structures = vec![];
+    let mut nicknames = HashSet::new();
+    for (idx, entry) in entries.iter().enumerate() {
+        let location = format!("{}: structures[{}]", path, idx);
+        let nickname = entry.get("nickname").and_then(Value::as_str).map(str::to_string);
+        let mut issue = |message: String| {
+            issues.push(BankIssue {
+                location: location.clone(),
+                structure: nickname.clone(),
+                message,
+            })
+        };
+
+        if let Err(e) = validate_cells(entry.get("cells").unwrap_or(&Value::Null)) {
+            issue(e);
+            continue;
+        }
+        let structure: Structure = match serde_json::from_value(entry.clone()) {
+            Ok(s) => s,
+            Err(e) => {
+                issue(e.to_string());
+                continue;
+            }
+        };
+        if !nicknames.insert(structure.nickname.clone()) {
+            issue("duplicate nickname, keeping the first one".to_string());
+            continue;
+        }
+
+        structures.push(structure);
+    }
+
+    println!("# structures on bank \"{}\": {}", &name, &structures.len());
+    for s in &structures {
         println!("{}", s.nickname);
     }
+    for issue in &issues {
+        println!("warning: {}", issue);
+    }
+
+    Ok(Structures {
+        name,
+        structures,
+        issues,
+        path: Some(path.to_string()),
+        modified: modified_time(path),
+    })
+}
+
+/// Like `load_from_json`, but a file that can't be read at all becomes an
+/// empty bank carrying the error. It keeps its path, so it is picked up
+/// again once the file is fixed.
+pub fn load_bank(path: &str) -> Structures {
+    load_from_json(path).unwrap_or_else(|issue| {
+        println!("error: {}", issue);
+        Structures {
+            name: path.to_string(),
+            structures: vec![],
+            issues: vec![issue],
+            path: Some(path.to_string()),
+            modified: modified_time(path),
+        }
+    })
+}
+
+/// Loads the legacy `structures.json` if present, then every `.json` bank in
+/// `dir` in name order.
+pub fn load_banks(legacy_file: &str, dir: &str) -> Vec<Structures> {
+    let mut paths: Vec<PathBuf> = vec![];
+    if Path::new(legacy_file).is_file() {
+        paths.push(PathBuf::from(legacy_file));
+    }
+    paths.extend(bank_files(dir));
+
+    paths.iter().map(|p| load_bank(&p.to_string_lossy())).collect()
+}
+
+fn modified_time(path: &str) -> Option<SystemTime> {
+    fs::metadata(path).and_then(|m| m.modified()).ok()
+}
+
+/// `.json` files in the bank directory, in name order.
+pub fn bank_files(dir: &str) -> Vec<PathBuf> {
+    let mut files: Vec<PathBuf> = match fs::read_dir(dir) {
+        Ok(entries) => entries
+            .filter_map(|e| e.ok().map(|e| e.path()))
+            .filter(|p| p.is_file() && p.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("json")))
+            .collect(),
+        Err(_) => vec![],
+    };
+    files.sort();
+    files
+}
 
-    Ok(structures)
+fn quote(s: &str) -> String {
+    serde_json::to_string(s).unwrap_or_default()
+}
+
+/// Trims dead rows and columns off a pattern. `None` if nothing is alive.
+pub fn crop_to_live(cells: &[Vec<u8>]) -> Option<Vec<Vec<u8>>> {
+    let live_rows: Vec<usize> = (0..cells.len()).filter(|y| cells[*y].contains(&1)).collect();
+    let width = cells.iter().map(|r| r.len()).max().unwrap_or(0);
+    let live_cols: Vec<usize> = (0..width)
+        .filter(|x| cells.iter().any(|r| r.get(*x) == Some(&1)))
+        .collect();
+
+    let (top, bottom) = (*live_rows.first()?, *live_rows.last()?);
+    let (left, right) = (*live_cols.first()?, *live_cols.last()?);
+    Some(
+        cells[top..=bottom]
+            .iter()
+            .map(|r| (left..=right).map(|x| r.get(x).copied().unwrap_or(0)).collect())
+            .collect(),
+    )
+}
+
+/// Adds an entry to the end of a bank file's `structures` array. The text is
+/// edited in place when possible, so everything already in the file stays
+/// exactly as written; the result is parsed again to make sure the entry
+/// landed in the array. If it didn't, the bank is parsed and rewritten.
+fn append_to_file(path: &str, s: &Structure) -> io::Result<()> {
+    let contents = fs::read_to_string(path)?;
+    let invalid = |msg: String| io::Error::new(io::ErrorKind::InvalidData, msg);
+
+    let mut bank: Value = serde_json::from_str(&contents).map_err(|e| invalid(e.to_string()))?;
+    let entry: Value = serde_json::from_str(&structure_to_json(s)).map_err(|e| invalid(e.to_string()))?;
+    let count = structure_count(&bank).ok_or_else(|| invalid("no \"structures\" array".to_string()))?;
+
+    if let Some(updated) = append_as_text(&contents, s) {
+        let parsed = serde_json::from_str::<Value>(&updated).ok();
+        let landed = parsed.as_ref().is_some_and(|v| {
+            structure_count(v) == Some(count + 1) && v["structures"][count] == entry
+        });
+        if landed {
+            return fs::write(path, updated);
+        }
+    }
+
+    if let Some(structures) = bank.get_mut("structures").and_then(Value::as_array_mut) {
+        structures.push(entry);
+    }
+    let updated = serde_json::to_string_pretty(&bank).map_err(|e| invalid(e.to_string()))?;
+    fs::write(path, updated + "\n")
+}
+
+fn structure_count(bank: &Value) -> Option<usize> {
+    bank.get("structures").and_then(Value::as_array).map(|a| a.len())
+}
+
+/// Inserts the entry before the last `]` ahead of the final `}`, which is
+/// the end of the `structures` array in the files this program writes.
+fn append_as_text(contents: &str, s: &Structure) -> Option<String> {
+    let body = contents.trim_end().strip_suffix('}')?;
+    let close = body.rfind(']')?;
+    let before = contents[..close].trim_end();
+    let separator = if before.ends_with('[') { "\n" } else { ",\n" };
+
+    Some(format!(
+        "{}{}{}\n    {}",
+        before,
+        separator,
+        structure_to_json(s),
+        &contents[close..]
+    ))
+}
+
+fn structure_to_json(s: &Structure) -> String {
+    let mut out = format!("        {{\n            \"nickname\": {},\n", quote(&s.nickname));
+    for (key, value) in s.metadata.json_fields() {
+        out.push_str(&format!("            \"{}\": {},\n", key, value));
+    }
+    out.push_str("            \"cells\": [\n");
+    for (idx, row) in s.cells.iter().enumerate() {
+        let row: Vec<String> = row.iter().map(|c| c.to_string()).collect();
+        out.push_str(&format!("                [{}]", row.join(", ")));
+        out.push_str(if idx + 1 < s.cells.len() { ",\n" } else { "\n" });
+    }
+    out.push_str("            ]\n        }");
+    out
 }
 
 impl Structures {
-    pub fn get_cells(&self, idx: usize) -> Vec<Vec<u8>> {
-        self.structures[idx].cells.clone()
+    pub fn empty(name: &str) -> Self {
+        Structures {
+            name: name.to_string(),
+            structures: vec![],
+            issues: vec![],
+            path: None,
+            modified: None,
+        }
+    }
+
+    pub fn path(&self) -> Option<&str> {
+        self.path.as_deref()
+    }
+
+    /// Whether the bank's file has been written since it was loaded or saved.
+    pub fn changed_on_disk(&self) -> bool {
+        match &self.path {
+            Some(path) => modified_time(path) != self.modified,
+            None => false,
+        }
+    }
+
+    /// Keeps the last good entries when the file no longer loads, recording
+    /// why so it shows up with the other bank problems.
+    pub fn reload_failed(&mut self, issue: BankIssue) {
+        self.issues = vec![issue];
+        self.modified = self.path.as_deref().and_then(modified_time);
+    }
+
+    pub fn find(&self, nickname: &str) -> Option<usize> {
+        self.structures.iter().position(|s| s.nickname == nickname)
+    }
+
+    /// A new bank holding this bank's entries followed by `other`'s. Entries
+    /// whose nickname is already taken are left out and counted.
+    pub fn merge(&self, other: &Structures, name: &str) -> (Structures, usize) {
+        let mut merged = Structures::empty(name);
+        let mut skipped = 0;
+        for s in self.structures.iter().chain(&other.structures) {
+            if merged.structures.iter().any(|m| m.nickname == s.nickname) {
+                skipped += 1;
+            } else {
+                merged.structures.push(s.clone());
+            }
+        }
+        (merged, skipped)
+    }
+
+    /// One bank per category present, plus one for entries without a
+    /// category.
+    pub fn split_by_category(&self) -> Vec<Structures> {
+        let mut banks: Vec<(Option<Category>, Structures)> = vec![];
+        for s in &self.structures {
+            let category = s.metadata.category;
+            let idx = match banks.iter().position(|(c, _)| *c == category) {
+                Some(idx) => idx,
+                None => {
+                    let label = category.map(|c| c.label()).unwrap_or("uncategorised");
+                    let name = format!("{} - {}", self.name, label);
+                    banks.push((category, Structures::empty(&name)));
+                    banks.len() - 1
+                }
+            };
+            banks[idx].1.structures.push(s.clone());
+        }
+        banks.into_iter().map(|(_, bank)| bank).collect()
+    }
+
+    /// Renders the bank in the same layout as the hand-written bank files,
+    /// with each row of cells on one line.
+    pub fn to_json(&self) -> String {
+        let mut out = format!("{{\n    \"name\": {},\n    \"structures\": [\n", quote(&self.name));
+        for (idx, s) in self.structures.iter().enumerate() {
+            out.push_str(&structure_to_json(s));
+            out.push_str(if idx + 1 < self.structures.len() { ",\n" } else { "\n" });
+        }
+        out.push_str("    ]\n}\n");
+        out
+    }
+
+    /// Adds a new entry and, if the bank came from a file, appends it there
+    /// too.
+    pub fn append_structure(&mut self, nickname: &str, cells: Vec<Vec<u8>>) -> Result<(), String> {
+        let nickname = nickname.trim();
+        if nickname.is_empty() {
+            return Err("structure needs a name".to_string());
+        }
+        if self.structures.iter().any(|s| s.nickname == nickname) {
+            return Err(format!("\"{}\" is already in bank \"{}\"", nickname, self.name));
+        }
+
+        let structure = Structure {
+            nickname: nickname.to_string(),
+            cells,
+            metadata: Metadata::default(),
+        };
+        if let Some(path) = &self.path {
+            append_to_file(path, &structure).map_err(|e| format!("{}: {}", path, e))?;
+            self.modified = modified_time(path);
+        }
+        self.structures.push(structure);
+        Ok(())
+    }
+
+    /// Writes the bank to `path` and remembers it as the bank's file.
+    pub fn save_to_json(&mut self, path: &str) -> io::Result<()> {
+        fs::write(path, self.to_json())?;
+        self.path = Some(path.to_string());
+        self.modified = modified_time(path);
+        Ok(())
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn get_cells(&self, idx: usize) -> Option<Vec<Vec<u8>>> {
+        self.structures.get(idx).map(|s| s.cells.clone())
     }
 
     pub fn len(&self) -> usize {
         self.structures.len()
     }
-}
 
+    pub fn cells(&self, idx: usize) -> Option<&[Vec<u8>]> {
+        self.structures.get(idx).map(|s| s.cells.as_slice())
+    }
+
+    /// Indices of every entry matching `filter`.
+    pub fn matching(&self, filter: &StructureFilter) -> Vec<usize> {
+        (0..self.len()).filter(|i| filter.matches(&self.structures[*i].metadata)).collect()
+    }
+
+    /// The metadata of an entry flattened into one string for searching.
+    pub fn search_text(&self, idx: usize) -> String {
+        let Some(metadata) = self.metadata(idx) else {
+            return String::new();
+        };
+        let mut text = metadata.summary();
+        for field in [&metadata.description, &metadata.url].into_iter().flatten() {
+            text.push(' ');
+            text.push_str(field);
+        }
+        text
+    }
+
+    pub fn nickname(&self, idx: usize) -> Option<&str> {
+        self.structures.get(idx).map(|s| s.nickname.as_str())
+    }
+
+    pub fn metadata(&self, idx: usize) -> Option<&Metadata> {
+        self.structures.get(idx).map(|s| &s.metadata)
+    }
+
+    /// Distinct rules named by entries in the bank, in order of appearance.
+    pub fn rules(&self) -> Vec<String> {
+        let mut rules: Vec<String> = vec![];
+        for s in &self.structures {
+            if let Some(rule) = &s.metadata.rule {
+                if !rules.iter().any(|r| r.eq_ignore_ascii_case(rule)) {
+                    rules.push(rule.clone());
+                }
+            }
+        }
+        rules
+    }
+
+    /// Steps from `idx` to the next (or previous) entry matching `filter`,
+    /// staying put if there is none in that direction.
+    pub fn step_matching(&self, idx: usize, filter: &StructureFilter, forward: bool) -> Option<usize> {
+        let matches = |i: &usize| filter.matches(&self.structures[*i].metadata);
+        let found = if forward {
+            (idx + 1..self.len()).find(matches)
+        } else {
+            (0..idx.min(self.len())).rev().find(matches)
+        };
+        found.or_else(|| self.first_matching(idx, filter))
+    }
+
+    /// `idx` itself if it matches `filter`, otherwise the first entry that
+    /// does.
+    pub fn first_matching(&self, idx: usize, filter: &StructureFilter) -> Option<usize> {
+        if self.structures.get(idx).is_some_and(|s| filter.matches(&s.metadata)) {
+            return Some(idx);
+        }
+        (0..self.len()).find(|i| filter.matches(&self.structures[*i].metadata))
+    }
+
+    pub fn issues(&self) -> &[BankIssue] {
+        &self.issues
+    }
+}
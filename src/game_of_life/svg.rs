@@ -0,0 +1,77 @@
+use std::{fmt::Write as _, fs, io};
+
+use sdl2::{pixels::Color, rect::Rect};
+
+use crate::game_of_life::board::Board;
+use crate::game_of_life::export::{clip_region, Theme};
+
+fn hex(color: Color) -> String {
+    format!("#{:02x}{:02x}{:02x}", color.r, color.g, color.b)
+}
+
+/// Renders a region of the board as SVG in cell units, so it stays sharp at
+/// any size. Horizontal runs of live cells are merged into one rect each.
+/// `scale` only sets the default display size in pixels.
+pub fn board_to_svg(board: &Board, region: Option<Rect>, scale: u32, theme: &Theme, grid_lines: bool) -> Result<String, String> {
+    let region = clip_region(board, region)?;
+    let (width, height) = (region.width(), region.height());
+    let scale = scale.max(1);
+    let mut svg = String::new();
+
+    let _ = writeln!(
+        svg,
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="{}" height="{}" viewBox="0 0 {} {}" shape-rendering="crispEdges">"#,
+        width * scale,
+        height * scale,
+        width,
+        height
+    );
+    let _ = writeln!(svg, r#"<rect width="{}" height="{}" fill="{}"/>"#, width, height, hex(theme.dead));
+
+    let _ = writeln!(svg, r#"<g fill="{}">"#, hex(theme.alive));
+    for y in 0..height as i32 {
+        let mut run_start: Option<i32> = None;
+        for x in 0..=width as i32 {
+            let alive = x < width as i32
+                && board.cells[(region.x() + x) as usize][(region.y() + y) as usize];
+            match (alive, run_start) {
+                (true, None) => run_start = Some(x),
+                (false, Some(start)) => {
+                    let _ = writeln!(
+                        svg,
+                        r#"<rect x="{}" y="{}" width="{}" height="1"/>"#,
+                        start,
+                        y,
+                        x - start
+                    );
+                    run_start = None;
+                }
+                _ => {}
+            }
+        }
+    }
+    let _ = writeln!(svg, "</g>");
+
+    if grid_lines {
+        let mut path = String::new();
+        for x in 0..=width {
+            let _ = write!(path, "M{} 0V{}", x, height);
+        }
+        for y in 0..=height {
+            let _ = write!(path, "M0 {}H{}", y, width);
+        }
+        let _ = writeln!(
+            svg,
+            r#"<path d="{}" stroke="{}" stroke-width="0.1" fill="none"/>"#,
+            path,
+            hex(theme.bg)
+        );
+    }
+
+    svg.push_str("</svg>\n");
+    Ok(svg)
+}
+
+pub fn write_svg(path: &str, svg: &str) -> io::Result<()> {
+    fs::write(path, svg)
+}
@@ -0,0 +1,239 @@
+//! Pattern transforms on `[y][x]` cell grids, the layout structures use.
+
+use std::collections::HashMap;
+
+use crate::game_of_life::board::{pattern_cells, BlendMode, Board};
+
+pub fn flip_horizontal(cells: &[Vec<u8>]) -> Vec<Vec<u8>> {
+    cells
+        .iter()
+        .map(|row| row.iter().rev().copied().collect())
+        .collect()
+}
+
+pub fn rotate_cw(cells: &[Vec<u8>]) -> Vec<Vec<u8>> {
+    let rows = cells.len();
+    let cols = cells.iter().map(|r| r.len()).max().unwrap_or(0);
+    let mut rotated = vec![vec![0u8; rows]; cols];
+    for (y, row) in cells.iter().enumerate() {
+        for (x, cell) in row.iter().enumerate() {
+            rotated[x][rows - 1 - y] = *cell;
+        }
+    }
+    rotated
+}
+
+/// One of the eight symmetries of the square, relative to a pattern as it is
+/// stored: mirror horizontally first if `mirrored`, then turn clockwise
+/// `quarter_turns` times.
+#[derive(Clone, Copy, PartialEq, Default)]
+pub struct Orientation {
+    quarter_turns: u8,
+    mirrored: bool,
+}
+
+impl Orientation {
+    pub fn rotated_cw(self) -> Self {
+        Orientation {
+            quarter_turns: (self.quarter_turns + 1) % 4,
+            ..self
+        }
+    }
+
+    pub fn rotated_ccw(self) -> Self {
+        Orientation {
+            quarter_turns: (self.quarter_turns + 3) % 4,
+            ..self
+        }
+    }
+
+    // Mirroring after a rotation is the same as mirroring first and rotating
+    // the other way, which keeps the mirror at the front.
+    pub fn flipped_horizontal(self) -> Self {
+        Orientation {
+            quarter_turns: (4 - self.quarter_turns) % 4,
+            mirrored: !self.mirrored,
+        }
+    }
+
+    pub fn flipped_vertical(self) -> Self {
+        Orientation {
+            quarter_turns: (6 - self.quarter_turns) % 4,
+            mirrored: !self.mirrored,
+        }
+    }
+
+    pub fn transposed(self) -> Self {
+        Orientation {
+            quarter_turns: (7 - self.quarter_turns) % 4,
+            mirrored: !self.mirrored,
+        }
+    }
+
+    pub fn apply(&self, cells: &[Vec<u8>]) -> Vec<Vec<u8>> {
+        let mut out = if self.mirrored {
+            flip_horizontal(cells)
+        } else {
+            cells.to_vec()
+        };
+        for _ in 0..self.quarter_turns {
+            out = rotate_cw(&out);
+        }
+        out
+    }
+
+    pub fn is_identity(&self) -> bool {
+        *self == Orientation::default()
+    }
+
+    pub fn label(&self) -> String {
+        let degrees = self.quarter_turns as u32 * 90;
+        if self.mirrored {
+            format!("mirrored, {}°", degrees)
+        } else {
+            format!("{}°", degrees)
+        }
+    }
+}
+
+/// Maps an offset from a symmetry centre to one of its images.
+type CellMap = fn(i32, i32) -> (i32, i32);
+
+/// Mirror and rotation modes for drawing. Every edit is repeated at each
+/// image of the edited cell under the mode's symmetries.
+#[derive(Clone, Copy, PartialEq)]
+pub enum Symmetry {
+    None,
+    /// Mirrored left to right, across a vertical axis.
+    Horizontal,
+    /// Mirrored top to bottom, across a horizontal axis.
+    Vertical,
+    /// Mirrored across the top-left to bottom-right diagonal.
+    Diagonal,
+    Rotate2,
+    Rotate4,
+    /// Both axis mirrors.
+    D4,
+    /// Every symmetry of the square.
+    D8,
+}
+
+impl Symmetry {
+    pub fn next(self) -> Self {
+        match self {
+            Symmetry::None => Symmetry::Horizontal,
+            Symmetry::Horizontal => Symmetry::Vertical,
+            Symmetry::Vertical => Symmetry::Diagonal,
+            Symmetry::Diagonal => Symmetry::Rotate2,
+            Symmetry::Rotate2 => Symmetry::Rotate4,
+            Symmetry::Rotate4 => Symmetry::D4,
+            Symmetry::D4 => Symmetry::D8,
+            Symmetry::D8 => Symmetry::None,
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Symmetry::None => "none",
+            Symmetry::Horizontal => "horizontal mirror",
+            Symmetry::Vertical => "vertical mirror",
+            Symmetry::Diagonal => "diagonal mirror",
+            Symmetry::Rotate2 => "2-fold rotation",
+            Symmetry::Rotate4 => "4-fold rotation",
+            Symmetry::D4 => "D4",
+            Symmetry::D8 => "D8",
+        }
+    }
+
+    /// Maps (u, v), an offset from the centre, to each of its images.
+    fn maps(&self) -> &'static [CellMap] {
+        const ID: CellMap = |u, v| (u, v);
+        const FLIP_X: CellMap = |u, v| (-u, v);
+        const FLIP_Y: CellMap = |u, v| (u, -v);
+        const HALF_TURN: CellMap = |u, v| (-u, -v);
+        const QUARTER_CW: CellMap = |u, v| (-v, u);
+        const QUARTER_CCW: CellMap = |u, v| (v, -u);
+        const DIAGONAL: CellMap = |u, v| (v, u);
+        const ANTI_DIAGONAL: CellMap = |u, v| (-v, -u);
+
+        match self {
+            Symmetry::None => &[ID],
+            Symmetry::Horizontal => &[ID, FLIP_X],
+            Symmetry::Vertical => &[ID, FLIP_Y],
+            Symmetry::Diagonal => &[ID, DIAGONAL],
+            Symmetry::Rotate2 => &[ID, HALF_TURN],
+            Symmetry::Rotate4 => &[ID, QUARTER_CW, HALF_TURN, QUARTER_CCW],
+            Symmetry::D4 => &[ID, FLIP_X, FLIP_Y, HALF_TURN],
+            Symmetry::D8 => &[ID, FLIP_X, FLIP_Y, HALF_TURN, QUARTER_CW, QUARTER_CCW, DIAGONAL, ANTI_DIAGONAL],
+        }
+    }
+
+    /// Every image of `cell` around `centre`, without repeats. The centre is
+    /// in half-cell units, so it can sit on a cell or on a grid corner: cell
+    /// (x, y) is centred at (2x + 1, 2y + 1).
+    pub fn images(&self, centre: (i32, i32), cell: (i32, i32)) -> Vec<(i32, i32)> {
+        let (u, v) = (2 * cell.0 + 1 - centre.0, 2 * cell.1 + 1 - centre.1);
+        let mut images: Vec<(i32, i32)> = vec![];
+        for map in self.maps() {
+            let (u, v) = map(u, v);
+            let image = ((centre.0 + u - 1).div_euclid(2), (centre.1 + v - 1).div_euclid(2));
+            if !images.contains(&image) {
+                images.push(image);
+            }
+        }
+        images
+    }
+
+    /// Stamps a pattern along with its images. Where images land on the
+    /// same cell it is alive if any of them is, and each cell is blended
+    /// once, so overlapping images can't undo each other.
+    pub fn stamp(&self, board: &mut Board, centre: (i32, i32), origin: (i32, i32), cells: &[Vec<u8>], mode: BlendMode) {
+        let mut targets: HashMap<(i32, i32), bool> = HashMap::new();
+        for (point, alive) in pattern_cells(origin, cells) {
+            for (x, y) in self.images(centre, point) {
+                if let Some(target) = board.wrap_coords(x, y) {
+                    *targets.entry(target).or_insert(false) |= alive;
+                }
+            }
+        }
+        for (target, alive) in targets {
+            board.blend_cell(target, alive, mode);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A horizontal mirror between columns 149 and 150.
+    const CENTRE: (i32, i32) = (300, 301);
+
+    #[test]
+    fn xor_stamp_across_the_axis_toggles_each_cell_once() {
+        let mut board = Board::with_size(300, 300);
+        Symmetry::Horizontal.stamp(&mut board, CENTRE, (149, 150), &[vec![1, 1]], BlendMode::Xor);
+        assert_eq!(board.live_cells(), vec![(149, 150), (150, 150)]);
+    }
+
+    #[test]
+    fn overwrite_stamp_keeps_live_images_over_dead_cells() {
+        // The dead cell at 150 is the image of the live one at 149, and
+        // the other way round; both should end up alive whatever the order.
+        let mut board = Board::with_size(300, 300);
+        Symmetry::Horizontal.stamp(&mut board, CENTRE, (149, 150), &[vec![1, 0]], BlendMode::Overwrite);
+        assert_eq!(board.live_cells(), vec![(149, 150), (150, 150)]);
+
+        let mut board = Board::with_size(300, 300);
+        Symmetry::Horizontal.stamp(&mut board, CENTRE, (149, 150), &[vec![0, 1]], BlendMode::Overwrite);
+        assert_eq!(board.live_cells(), vec![(149, 150), (150, 150)]);
+    }
+
+    #[test]
+    fn overwrite_stamp_clears_where_no_image_is_alive() {
+        let mut board = Board::with_size(300, 300);
+        board.set_live_cells(&[(148, 150), (151, 150)]);
+        Symmetry::Horizontal.stamp(&mut board, CENTRE, (148, 150), &[vec![0, 1]], BlendMode::Overwrite);
+        assert_eq!(board.live_cells(), vec![(149, 150), (150, 150)]);
+    }
+}
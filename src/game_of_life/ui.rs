@@ -0,0 +1,121 @@
+use std::path::Path;
+
+use sdl2::{
+    pixels::Color,
+    rect::Rect,
+    render::{Canvas, TextureCreator},
+    ttf::Font,
+    video::{Window, WindowContext},
+};
+
+pub const FONT_SIZE: u16 = 14;
+pub const SIDEBAR_HEADER_HEIGHT: i32 = 32;
+pub const SIDEBAR_ROW_HEIGHT: i32 = 52;
+pub const THUMBNAIL_SIZE: u32 = 44;
+/// Room under the sidebar list for the selected entry's description and url.
+pub const SIDEBAR_DETAIL_HEIGHT: i32 = 112;
+
+/// Places a font is looked for, starting with one dropped into the repo's
+/// `img` directory.
+const FONT_PATHS: [&str; 7] = [
+    "img/font.ttf",
+    "/usr/share/fonts/truetype/dejavu/DejaVuSans.ttf",
+    "/usr/share/fonts/TTF/DejaVuSans.ttf",
+    "/usr/share/fonts/dejavu/DejaVuSans.ttf",
+    "/System/Library/Fonts/Supplemental/Arial.ttf",
+    "/Library/Fonts/Arial.ttf",
+    "C:\\Windows\\Fonts\\arial.ttf",
+];
+
+pub fn find_font() -> Option<&'static str> {
+    FONT_PATHS.iter().copied().find(|p| Path::new(p).is_file())
+}
+
+/// Draws one line of text with its top-left corner at (x, y), cut off at
+/// `max_width`. Returns the height drawn.
+pub fn draw_text(
+    canvas: &mut Canvas<Window>,
+    texture_creator: &TextureCreator<WindowContext>,
+    font: &Font,
+    text: &str,
+    (x, y): (i32, i32),
+    max_width: u32,
+    color: Color,
+) -> Result<u32, String> {
+    if text.is_empty() {
+        return Ok(0);
+    }
+
+    let surface = font
+        .render(text)
+        .blended(color)
+        .map_err(|e| e.to_string())?;
+    let texture = texture_creator
+        .create_texture_from_surface(&surface)
+        .map_err(|e| e.to_string())?;
+    let width = surface.width().min(max_width);
+    let src = Rect::new(0, 0, width, surface.height());
+    canvas.copy(&texture, src, Rect::new(x, y, width, surface.height()))?;
+
+    Ok(surface.height())
+}
+
+/// Breaks text into lines no wider than `max_width`, splitting at spaces.
+/// A single word too wide for a line gets a line to itself.
+pub fn wrap_text(font: &Font, text: &str, max_width: u32) -> Vec<String> {
+    let mut lines = vec![];
+    let mut line = String::new();
+    for word in text.split_whitespace() {
+        let candidate = if line.is_empty() {
+            word.to_string()
+        } else {
+            format!("{} {}", line, word)
+        };
+        let fits = font.size_of(&candidate).map(|(w, _)| w <= max_width).unwrap_or(true);
+        if fits || line.is_empty() {
+            line = candidate;
+        } else {
+            lines.push(std::mem::replace(&mut line, word.to_string()));
+        }
+    }
+    if !line.is_empty() {
+        lines.push(line);
+    }
+    lines
+}
+
+/// Draws a pattern scaled to fit `bounds`, centred, at no less than one pixel
+/// per cell.
+pub fn draw_thumbnail(
+    canvas: &mut Canvas<Window>,
+    cells: &[Vec<u8>],
+    bounds: Rect,
+    alive: Color,
+    dead: Color,
+) {
+    let rows = cells.len() as u32;
+    let cols = cells.iter().map(|r| r.len()).max().unwrap_or(0) as u32;
+    if rows == 0 || cols == 0 {
+        return;
+    }
+
+    let px = (bounds.width() / cols).min(bounds.height() / rows).max(1);
+    let x0 = bounds.x() + (bounds.width() as i32 - (cols * px) as i32).max(0) / 2;
+    let y0 = bounds.y() + (bounds.height() as i32 - (rows * px) as i32).max(0) / 2;
+
+    canvas.set_draw_color(dead);
+    let _ = canvas.fill_rect(Rect::new(x0, y0, cols * px, rows * px).intersection(bounds));
+
+    canvas.set_draw_color(alive);
+    for (y, row) in cells.iter().enumerate() {
+        for (x, cell) in row.iter().enumerate() {
+            if *cell == 0 {
+                continue;
+            }
+            let cell_rect = Rect::new(x0 + x as i32 * px as i32, y0 + y as i32 * px as i32, px, px);
+            if let Some(clipped) = cell_rect.intersection(bounds) {
+                let _ = canvas.fill_rect(clipped);
+            }
+        }
+    }
+}